@@ -1,8 +1,8 @@
 #![deny(warnings)]
 
 use {
-    egui_speedy2d::{WindowHandler, WindowWrapper},
-    speedy2d::{color::Color, window::WindowHelper, Graphics2D, Window},
+    egui_speedy2d::{Canvas, WindowHandler, WindowWrapper},
+    speedy2d::{color::Color, window::WindowHelper, Window},
 };
 
 fn main() {
@@ -16,14 +16,16 @@ struct MyWindowHandler {}
 impl WindowHandler for MyWindowHandler {
     fn on_draw(
         &mut self,
-        helper: &mut WindowHelper,
-        graphics: &mut Graphics2D,
+        helper: Option<&mut WindowHelper>,
+        canvas: &mut dyn Canvas,
         egui_ctx: &egui::Context,
     ) {
-        graphics.clear_screen(Color::WHITE);
+        canvas.clear_screen(Color::WHITE);
         egui::Window::new("Hello").show(egui_ctx, |ui| {
             ui.label("World !");
         });
-        helper.request_redraw();
+        if let Some(helper) = helper {
+            helper.request_redraw();
+        }
     }
 }
@@ -0,0 +1,234 @@
+//! Accessibility through [AccessKit](https://accesskit.dev).
+//!
+//! With the `accesskit` feature, [`WindowWrapper::with_accesskit`] turns on
+//! egui's accessibility tree and hands it to a [`TreeConsumer`] every frame,
+//! typically an AccessKit platform adapter. Action requests coming back from
+//! assistive technologies go through an [`ActionQueue`] and reach egui on the
+//! next frame.
+//!
+//! The [`Harness`](crate::testing::Harness) turns the tree on as well, so tests
+//! can check it with no screen reader attached:
+//!
+//! ```
+//! use egui::accesskit::{Action, ActionRequest, Role};
+//! use egui_speedy2d::testing::Harness;
+//!
+//! let mut clicks = 0;
+//! let mut ui = |ctx: &egui::Context| {
+//!     egui::CentralPanel::default().show(ctx, |ui| {
+//!         if ui.button("Click me").clicked() {
+//!             clicks += 1;
+//!         }
+//!     });
+//! };
+//! let mut harness = Harness::new((200, 100));
+//! harness.run(1, &mut ui);
+//!
+//! let tree = harness.accesskit_tree().unwrap();
+//! let (button, _) = tree
+//!     .nodes
+//!     .iter()
+//!     .find(|(_, node)| node.role() == Role::Button && node.name() == Some("Click me"))
+//!     .unwrap();
+//!
+//! let request = ActionRequest { action: Action::Default, target: *button, data: None };
+//! harness.on_frame(1, [egui::Event::AccessKitActionRequest(request)]);
+//! harness.run(1, &mut ui);
+//! assert_eq!(clicks, 1);
+//! ```
+//!
+//! [`WindowWrapper::with_accesskit`]: crate::WindowWrapper::with_accesskit
+
+use egui::{
+    accesskit::{ActionHandler, ActionRequest, TreeUpdate},
+    mutex::Mutex,
+};
+use std::sync::Arc;
+
+/// Receives the accessibility tree built by egui, once per frame.
+///
+/// egui rebuilds the nodes of every widget each frame, so each update holds
+/// the whole tree.
+pub trait TreeConsumer {
+    /// Takes the tree of the frame that just ended, before it is drawn.
+    fn update_tree(&mut self, update: TreeUpdate);
+}
+
+impl<F: FnMut(TreeUpdate)> TreeConsumer for F {
+    fn update_tree(&mut self, update: TreeUpdate) {
+        self(update)
+    }
+}
+
+/// Action requests from assistive technologies, waiting to be given to egui.
+///
+/// It can be cloned and sent to other threads, and implements AccessKit's
+/// [`ActionHandler`] so that it can be passed to platform adapters as is.
+///
+/// The requests are read when the next frame is drawn. An idle window draws
+/// no frame until it is woken up, which [`ActionQueue::set_waker`] does, for
+/// example by sending a user event to the window:
+///
+/// ```no_run
+/// # use egui_speedy2d::{accessibility::ActionQueue, WindowHandler};
+/// # use speedy2d::window::{WindowCreationOptions, WindowHelper, WindowSize};
+/// struct Wake;
+///
+/// struct App;
+///
+/// impl WindowHandler<Wake> for App {
+///     fn on_user_event(&mut self, helper: &mut WindowHelper<Wake>, _: Wake, _: &egui::Context) {
+///         helper.request_redraw();
+///     }
+/// }
+///
+/// let size = WindowSize::PhysicalPixels((640, 480).into());
+/// let options = WindowCreationOptions::new_windowed(size, None);
+/// let window = speedy2d::Window::<Wake>::new_with_user_events("App", options).unwrap();
+/// let sender = window.create_user_event_sender();
+/// let wrapper = egui_speedy2d::WindowWrapper::new(App).with_accesskit(|_| {});
+/// wrapper.accesskit_actions().set_waker(move || {
+///     let _ = sender.send_event(Wake);
+/// });
+/// window.run_loop(wrapper)
+/// ```
+#[derive(Clone, Default)]
+pub struct ActionQueue(Arc<Mutex<Queue>>);
+
+#[derive(Default)]
+struct Queue {
+    requests: Vec<ActionRequest>,
+    waker: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl ActionQueue {
+    /// Queues a request for the next frame, and wakes the window up.
+    pub fn push(&self, request: ActionRequest) {
+        let waker = {
+            let mut queue = self.0.lock();
+            queue.requests.push(request);
+            queue.waker.clone()
+        };
+        if let Some(waker) = waker {
+            waker();
+        }
+    }
+
+    /// Sets what [`ActionQueue::push`] calls to wake the window up, from
+    /// whichever thread pushes the request.
+    pub fn set_waker(&self, waker: impl Fn() + Send + Sync + 'static) {
+        self.0.lock().waker = Some(Arc::new(waker));
+    }
+
+    /// Takes the queued requests, as egui events.
+    pub(crate) fn take_events(&self) -> Vec<egui::Event> {
+        std::mem::take(&mut self.0.lock().requests)
+            .into_iter()
+            .map(egui::Event::AccessKitActionRequest)
+            .collect()
+    }
+}
+
+impl ActionHandler for ActionQueue {
+    fn do_action(&mut self, request: ActionRequest) {
+        self.push(request);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, SoftwareRenderer, WindowHandler, WindowWrapper};
+    use egui::accesskit::{Action, Role};
+    use speedy2d::window::WindowHelper;
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    #[derive(Default)]
+    struct App {
+        clicks: Rc<Cell<usize>>,
+    }
+
+    impl WindowHandler for App {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &egui::Context,
+        ) {
+            egui::CentralPanel::default().show(egui_ctx, |ui| {
+                if ui.button("Click me").clicked() {
+                    self.clicks.set(self.clicks.get() + 1);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn trees_go_out_and_action_requests_come_back() {
+        let app = App::default();
+        let clicks = app.clicks.clone();
+        let updates = Rc::new(RefCell::new(vec![]));
+        let mut window = WindowWrapper::headless(app).with_accesskit({
+            let updates = updates.clone();
+            move |update| updates.borrow_mut().push(update)
+        });
+        let actions = window.accesskit_actions();
+        let mut frame = || {
+            let mut renderer = SoftwareRenderer::new((200, 100));
+            let end = window.frame(&mut renderer, None);
+            assert!(end.errors.is_empty());
+        };
+        frame();
+        frame();
+        assert_eq!(updates.borrow().len(), 2);
+
+        let button = {
+            let updates = updates.borrow();
+            let tree = updates.last().unwrap();
+            let (button, _) = tree
+                .nodes
+                .iter()
+                .find(|(_, node)| node.role() == Role::Button && node.name() == Some("Click me"))
+                .unwrap();
+            *button
+        };
+        actions.push(ActionRequest {
+            action: Action::Default,
+            target: button,
+            data: None,
+        });
+        frame();
+        assert_eq!(clicks.get(), 1);
+        assert!(actions.take_events().is_empty());
+    }
+
+    #[test]
+    fn requests_pushed_from_other_threads_wake_the_window() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let queue = ActionQueue::default();
+        let wakes = Arc::new(AtomicUsize::new(0));
+        queue.set_waker({
+            let wakes = wakes.clone();
+            move || {
+                wakes.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        let mut handler = queue.clone();
+        std::thread::spawn(move || {
+            handler.do_action(ActionRequest {
+                action: Action::Focus,
+                target: egui::accesskit::NodeId(1),
+                data: None,
+            })
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+        assert_eq!(queue.take_events().len(), 1);
+    }
+}
@@ -0,0 +1,23 @@
+//! Shows the egui UI streamed by a `MirrorServer`, and sends it the input.
+//!
+//! Usage: `egui_speedy2d_viewer [ADDRESS]`, the address defaulting to
+//! `127.0.0.1:7071`.
+
+use egui_speedy2d::mirror::MirrorViewer;
+use speedy2d::Window;
+
+fn main() {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7071".to_owned());
+    let viewer = match MirrorViewer::connect(&addr) {
+        Ok(viewer) => viewer,
+        Err(error) => {
+            eprintln!("failed to connect to {addr}: {error}");
+            std::process::exit(1);
+        }
+    };
+    let window = Window::new_centered(format!("egui_speedy2d viewer - {addr}"), (640, 480))
+        .expect("failed to create the window");
+    window.run_loop(viewer)
+}
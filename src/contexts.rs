@@ -0,0 +1,254 @@
+//! Named egui contexts drawn by a [`WindowWrapper`](crate::WindowWrapper)
+//! next to its main one.
+
+use crate::{ContextTransformExt, Painter, Transform};
+use egui::{Context, Id, RawInput};
+use std::sync::Arc;
+
+/// Finds the named contexts of the [`WindowWrapper`](crate::WindowWrapper)
+/// owning the main egui context.
+///
+/// Named contexts are added with
+/// [`WindowWrapper::with_context`](crate::WindowWrapper::with_context). Their
+/// frame is running while the handler's
+/// [`on_draw`](crate::WindowHandler::on_draw) is, so their UI is built there:
+///
+/// ```
+/// use egui_speedy2d::ContextNamedExt;
+/// # use egui_speedy2d::Canvas;
+/// # use speedy2d::window::WindowHelper;
+///
+/// struct Game;
+///
+/// impl egui_speedy2d::WindowHandler for Game {
+///     fn on_draw(&mut self, _: Option<&mut WindowHelper>, _: &mut dyn Canvas, egui_ctx: &egui::Context) {
+///         egui::Window::new("Inventory").show(egui_ctx, |ui| ui.label("Empty"));
+///         if let Some(hud) = egui_ctx.named_context("hud") {
+///             egui::Area::new("health".into()).show(&hud, |ui| ui.label("100 HP"));
+///         }
+///     }
+/// }
+///
+/// let window = egui_speedy2d::WindowWrapper::<()>::new(Game).with_context("hud", -1);
+/// assert!(window.context("hud").is_some());
+/// ```
+pub trait ContextNamedExt {
+    /// Returns the context added under `name`, if any.
+    fn named_context(&self, name: &str) -> Option<Context>;
+}
+
+impl ContextNamedExt for Context {
+    fn named_context(&self, name: &str) -> Option<Context> {
+        let contexts: Option<Names> = self.data(|d| d.get_temp(names_id()));
+        contexts?
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, ctx)| ctx.clone())
+    }
+}
+
+type Names = Arc<Vec<(String, Context)>>;
+
+fn names_id() -> Id {
+    Id::new("egui_speedy2d::named_contexts")
+}
+
+/// A context of a [`WindowWrapper`](crate::WindowWrapper) besides the main
+/// one, with its own style, memory, zoom and textures.
+pub(crate) struct NamedContext<I> {
+    pub(crate) name: String,
+    pub(crate) ctx: Context,
+    pub(crate) priority: i32,
+    pub(crate) painter: Painter<I>,
+}
+
+/// Makes `contexts` reachable from `main` with
+/// [`ContextNamedExt::named_context`].
+pub(crate) fn publish<I>(main: &Context, contexts: &[NamedContext<I>]) {
+    let names: Names = Arc::new(
+        contexts
+            .iter()
+            .map(|c| (c.name.clone(), c.ctx.clone()))
+            .collect(),
+    );
+    main.data_mut(|d| d.insert_temp(names_id(), names));
+}
+
+/// Returns the order the contexts are drawn in, bottom first: `None` is the
+/// main context, `Some(i)` is `contexts[i]`.
+///
+/// `contexts` are sorted by priority. The main context has priority 0, and
+/// is drawn under the named contexts of the same priority.
+pub(crate) fn render_order<I>(contexts: &[NamedContext<I>]) -> Vec<Option<usize>> {
+    let main = contexts.partition_point(|c| c.priority < 0);
+    let mut order: Vec<_> = (0..contexts.len()).map(Some).collect();
+    order.insert(main, None);
+    order
+}
+
+/// Returns the output of the main context and of each of `contexts`, in the
+/// order the contexts get input, topmost first.
+pub(crate) fn input_order<'a, I>(
+    contexts: &[NamedContext<I>],
+    main: &'a egui::FullOutput,
+    outputs: &'a [egui::FullOutput],
+) -> Vec<&'a egui::FullOutput> {
+    render_order(contexts)
+        .into_iter()
+        .rev()
+        .map(|slot| match slot {
+            Some(i) => &outputs[i],
+            None => main,
+        })
+        .collect()
+}
+
+/// Splits the input of a frame between the main context and the named
+/// ones, and returns the input of the main context and of each of
+/// `contexts`.
+///
+/// Contexts get input in the reverse of the render order, topmost first.
+/// Once a context is under the pointer or using it, the contexts after it
+/// lose the pointer events. Once a context wants the keyboard, the contexts
+/// after it lose the keyboard events. This is decided from the last frame.
+///
+/// Pointer positions are in the space of the main context, and are moved
+/// into the space of each named context through their [`Transform`].
+pub(crate) fn route<I>(
+    main: &Context,
+    contexts: &[NamedContext<I>],
+    raw_input: RawInput,
+) -> (RawInput, Vec<RawInput>) {
+    let mut main_input = None;
+    let mut inputs = vec![None; contexts.len()];
+    let mut pointer_taken = false;
+    let mut keyboard_taken = false;
+    let to_screen = main.paint_transform();
+
+    for slot in render_order(contexts).into_iter().rev() {
+        let ctx = match slot {
+            Some(i) => &contexts[i].ctx,
+            None => main,
+        };
+        let mut input = raw_input.clone();
+        let had_pointer_events = input.events.iter().any(is_pointer_event);
+        input.events.retain(|event| {
+            !(pointer_taken && is_pointer_event(event)
+                || keyboard_taken && is_keyboard_event(event))
+        });
+        if pointer_taken && had_pointer_events {
+            input.events.push(egui::Event::PointerGone);
+        }
+        if slot.is_some() {
+            if let Some(from_screen) = ctx.paint_transform().inverse() {
+                move_pointer_events(&mut input.events, to_screen.then(from_screen));
+            }
+        }
+        pointer_taken |= ctx.is_pointer_over_area() || ctx.is_using_pointer();
+        keyboard_taken |= ctx.wants_keyboard_input();
+        match slot {
+            Some(i) => inputs[i] = Some(input),
+            None => main_input = Some(input),
+        }
+    }
+
+    (
+        main_input.unwrap_or(raw_input),
+        inputs.into_iter().map(Option::unwrap_or_default).collect(),
+    )
+}
+
+fn is_pointer_event(event: &egui::Event) -> bool {
+    matches!(
+        event,
+        egui::Event::PointerMoved(_)
+            | egui::Event::MouseMoved(_)
+            | egui::Event::PointerButton { .. }
+            | egui::Event::PointerGone
+            | egui::Event::Scroll(_)
+            | egui::Event::Zoom(_)
+            | egui::Event::Touch { .. }
+            | egui::Event::MouseWheel { .. }
+    )
+}
+
+fn is_keyboard_event(event: &egui::Event) -> bool {
+    matches!(
+        event,
+        egui::Event::Copy
+            | egui::Event::Cut
+            | egui::Event::Paste(_)
+            | egui::Event::Text(_)
+            | egui::Event::Key { .. }
+            | egui::Event::CompositionStart
+            | egui::Event::CompositionUpdate(_)
+            | egui::Event::CompositionEnd(_)
+    )
+}
+
+fn move_pointer_events(events: &mut [egui::Event], transform: Transform) {
+    for event in events {
+        match event {
+            egui::Event::PointerMoved(pos)
+            | egui::Event::PointerButton { pos, .. }
+            | egui::Event::Touch { pos, .. } => *pos = transform.apply(*pos),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::Driver, Canvas, ContextNamedExt, WindowHandler, WindowWrapper};
+    use speedy2d::window::WindowHelper;
+    use std::{cell::Cell, rc::Rc};
+
+    /// A button of the main context, half covered by an area of the "hud"
+    /// context.
+    struct Covered(Rc<Cell<usize>>);
+
+    impl WindowHandler for Covered {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &egui::Context,
+        ) {
+            egui::Area::new("button".into())
+                .fixed_pos(egui::Pos2::ZERO)
+                .show(egui_ctx, |ui| {
+                    let button = egui::Button::new("").min_size(egui::vec2(40., 40.));
+                    if ui.add(button).clicked() {
+                        self.0.set(self.0.get() + 1);
+                    }
+                });
+            if let Some(hud) = egui_ctx.named_context("hud") {
+                egui::Area::new("hud".into())
+                    .fixed_pos(egui::pos2(20., 0.))
+                    .show(&hud, |ui| ui.allocate_space(egui::vec2(40., 40.)));
+            }
+        }
+    }
+
+    #[test]
+    fn contexts_in_front_take_the_pointer_over_their_areas() {
+        let clicks = Rc::new(Cell::new(0));
+        let window = WindowWrapper::headless(Covered(clicks.clone())).with_context("hud", 1);
+        let mut driver = Driver::new(window, (100, 100));
+
+        driver.click_at(egui::pos2(30., 10.));
+        assert_eq!(clicks.get(), 0);
+        driver.click_at(egui::pos2(10., 10.));
+        assert_eq!(clicks.get(), 1);
+    }
+
+    #[test]
+    fn contexts_behind_leave_the_pointer_to_the_main_one() {
+        let clicks = Rc::new(Cell::new(0));
+        let window = WindowWrapper::headless(Covered(clicks.clone())).with_context("hud", -1);
+        let mut driver = Driver::new(window, (100, 100));
+
+        driver.click_at(egui::pos2(30., 10.));
+        assert_eq!(clicks.get(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+//! Vector drawings of egui's cursor icons, for windows where the OS cursor
+//! can only be shown or hidden.
+
+use egui::{pos2, vec2, Color32, CursorIcon, Pos2, Shape, Stroke, Vec2};
+
+/// A piece of a cursor drawing, in pixels relative to the hotspot.
+enum Part {
+    /// A convex polygon.
+    Polygon(Vec<Pos2>),
+    Line(Pos2, Pos2),
+    Circle(Pos2, f32),
+}
+
+/// Returns the shapes drawing `icon` with its hotspot at `pos`: white parts
+/// with a black outline, so that the cursor shows on any background.
+pub(crate) fn shapes(icon: CursorIcon, pos: Pos2) -> Vec<Shape> {
+    let parts = parts(icon);
+    let at = |p: &Pos2| pos + p.to_vec2();
+    let outline = parts.iter().map(|part| match part {
+        Part::Polygon(points) => Shape::convex_polygon(
+            points.iter().map(at).collect(),
+            Color32::BLACK,
+            Stroke::new(2., Color32::BLACK),
+        ),
+        Part::Line(a, b) => Shape::line_segment([at(a), at(b)], Stroke::new(4., Color32::BLACK)),
+        Part::Circle(center, radius) => {
+            Shape::circle_stroke(at(center), *radius, Stroke::new(4., Color32::BLACK))
+        }
+    });
+    let fill = parts.iter().map(|part| match part {
+        Part::Polygon(points) => Shape::convex_polygon(
+            points.iter().map(at).collect(),
+            Color32::WHITE,
+            Stroke::NONE,
+        ),
+        Part::Line(a, b) => Shape::line_segment([at(a), at(b)], Stroke::new(2., Color32::WHITE)),
+        Part::Circle(center, radius) => {
+            Shape::circle_stroke(at(center), *radius, Stroke::new(2., Color32::WHITE))
+        }
+    });
+    outline.chain(fill).collect()
+}
+
+fn parts(icon: CursorIcon) -> Vec<Part> {
+    use CursorIcon::*;
+    match icon {
+        None => vec![],
+        Default | ContextMenu | Help | Progress | Wait | Cell | Alias | Copy => arrow(),
+        PointingHand => vec![
+            rect(pos2(-1.5, 0.), pos2(1.5, 9.)),
+            rect(pos2(-4., 7.), pos2(7., 17.)),
+        ],
+        Grab => {
+            let mut parts = (0..4)
+                .map(|i| {
+                    let x = -5. + 3.5 * i as f32;
+                    rect(pos2(x, -8.), pos2(x + 2.5, -2.))
+                })
+                .collect::<Vec<_>>();
+            parts.push(rect(pos2(-6., -3.), pos2(8., 7.)));
+            parts
+        }
+        Grabbing => {
+            let mut parts = (0..4)
+                .map(|i| {
+                    let x = -5. + 3.5 * i as f32;
+                    rect(pos2(x, -4.), pos2(x + 2.5, -2.))
+                })
+                .collect::<Vec<_>>();
+            parts.push(rect(pos2(-6., -3.), pos2(8., 6.)));
+            parts
+        }
+        Crosshair => vec![
+            Part::Line(pos2(-8., 0.), pos2(8., 0.)),
+            Part::Line(pos2(0., -8.), pos2(0., 8.)),
+        ],
+        Text => i_beam(vec2(0., 1.)),
+        VerticalText => i_beam(vec2(1., 0.)),
+        Move | AllScroll => [vec2(1., 0.), vec2(0., 1.)]
+            .into_iter()
+            .flat_map(double_arrow)
+            .collect(),
+        NoDrop | NotAllowed => vec![
+            Part::Circle(Pos2::ZERO, 7.),
+            Part::Line(pos2(-5., -5.), pos2(5., 5.)),
+        ],
+        ResizeHorizontal | ResizeEast | ResizeWest | ResizeColumn => double_arrow(vec2(1., 0.)),
+        ResizeVertical | ResizeNorth | ResizeSouth | ResizeRow => double_arrow(vec2(0., 1.)),
+        ResizeNeSw | ResizeNorthEast | ResizeSouthWest => double_arrow(vec2(1., -1.).normalized()),
+        ResizeNwSe | ResizeNorthWest | ResizeSouthEast => double_arrow(vec2(1., 1.).normalized()),
+        ZoomIn | ZoomOut => {
+            let mut parts = vec![
+                Part::Circle(pos2(-2., -2.), 6.),
+                Part::Line(pos2(2.5, 2.5), pos2(8., 8.)),
+                Part::Line(pos2(-5., -2.), pos2(1., -2.)),
+            ];
+            if icon == ZoomIn {
+                parts.push(Part::Line(pos2(-2., -5.), pos2(-2., 1.)));
+            }
+            parts
+        }
+    }
+}
+
+/// The usual arrow, with its hotspot at the tip.
+fn arrow() -> Vec<Part> {
+    vec![
+        Part::Polygon(vec![pos2(0., 0.), pos2(11., 11.), pos2(0., 15.)]),
+        Part::Polygon(vec![
+            pos2(2.5, 11.),
+            pos2(5.5, 10.),
+            pos2(8.5, 17.),
+            pos2(5.5, 18.),
+        ]),
+    ]
+}
+
+fn rect(min: Pos2, max: Pos2) -> Part {
+    Part::Polygon(vec![min, pos2(max.x, min.y), max, pos2(min.x, max.y)])
+}
+
+/// A text cursor along `dir`.
+fn i_beam(dir: Vec2) -> Vec<Part> {
+    let side = dir.rot90();
+    vec![
+        Part::Line(Pos2::ZERO - 8. * dir, Pos2::ZERO + 8. * dir),
+        Part::Line(
+            Pos2::ZERO - 8. * dir - 3. * side,
+            Pos2::ZERO - 8. * dir + 3. * side,
+        ),
+        Part::Line(
+            Pos2::ZERO + 8. * dir - 3. * side,
+            Pos2::ZERO + 8. * dir + 3. * side,
+        ),
+    ]
+}
+
+/// A line along `dir` with an arrow head at both ends.
+fn double_arrow(dir: Vec2) -> Vec<Part> {
+    let side = dir.rot90();
+    let head = |tip: Vec2, back: Vec2| {
+        Part::Polygon(vec![
+            Pos2::ZERO + tip,
+            Pos2::ZERO + back + 4. * side,
+            Pos2::ZERO + back - 4. * side,
+        ])
+    };
+    vec![
+        Part::Line(Pos2::ZERO - 6. * dir, Pos2::ZERO + 6. * dir),
+        head(10. * dir, 5. * dir),
+        head(-10. * dir, -5. * dir),
+    ]
+}
@@ -0,0 +1,319 @@
+//! Debug drawing of what [`WindowWrapper::draw`](crate::WindowWrapper::draw)
+//! sends to the renderer.
+
+use crate::{color_from_egui, vec2_from_egui, Canvas, Painter, Renderer, Transform};
+use egui::{Context, Id, Rect};
+use speedy2d::{color::Color, dimen::Vec2, shape::Rectangle};
+
+/// Debug drawings made over the egui UI, to see why something renders wrong.
+///
+/// They are drawn after the UI of the context they are set on, the main one or
+/// a [named context](crate::WindowWrapper::with_context), and can be switched
+/// at runtime from any [`WindowHandler`](crate::WindowHandler) callback with
+/// [`ContextDebugPaintExt::set_debug_paint`]:
+///
+/// ```
+/// use egui_speedy2d::{ContextDebugPaintExt, DebugPaint};
+///
+/// let ctx = egui::Context::default();
+/// assert!(!ctx.debug_paint().is_enabled());
+///
+/// ctx.set_debug_paint(DebugPaint { wireframe: true, ..Default::default() });
+/// assert!(ctx.debug_paint().wireframe);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DebugPaint {
+    /// Outlines the clip rect of every mesh.
+    pub clip_rects: bool,
+    /// Draws the edges of every triangle.
+    pub wireframe: bool,
+    /// Tints every mesh with a color picked from its texture id.
+    pub texture_colors: bool,
+    /// Shows the font atlas and the user textures in a panel at the bottom of
+    /// the window.
+    pub texture_panel: bool,
+}
+
+impl DebugPaint {
+    /// Returns whether anything is drawn.
+    pub fn is_enabled(&self) -> bool {
+        self.clip_rects || self.wireframe || self.texture_colors || self.texture_panel
+    }
+
+    /// Shows a checkbox for each drawing.
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.clip_rects, "Clip rects");
+        ui.checkbox(&mut self.wireframe, "Wireframe");
+        ui.checkbox(&mut self.texture_colors, "Color by texture");
+        ui.checkbox(&mut self.texture_panel, "Texture panel");
+    }
+}
+
+/// Switches the [`DebugPaint`] drawings of an egui context.
+pub trait ContextDebugPaintExt {
+    /// Sets the debug drawings made over the UI, from the next frame drawn.
+    fn set_debug_paint(&self, debug_paint: DebugPaint);
+
+    /// Returns the debug drawings made over the UI.
+    fn debug_paint(&self) -> DebugPaint;
+}
+
+impl ContextDebugPaintExt for Context {
+    fn set_debug_paint(&self, debug_paint: DebugPaint) {
+        self.data_mut(|d| d.insert_temp(debug_paint_id(), debug_paint));
+    }
+
+    fn debug_paint(&self) -> DebugPaint {
+        self.data(|d| d.get_temp(debug_paint_id()))
+            .unwrap_or_default()
+    }
+}
+
+fn debug_paint_id() -> Id {
+    Id::new("egui_speedy2d::debug_paint")
+}
+
+/// Height of the texture panel, in pixels.
+const PANEL_HEIGHT: f32 = 160.;
+const PANEL_MARGIN: f32 = 8.;
+
+/// Draws the enabled debug drawings of a frame, once it is painted.
+pub(crate) fn paint<R: Renderer + Canvas>(
+    debug_paint: DebugPaint,
+    primitives: &[egui::ClippedPrimitive],
+    transform: Transform,
+    screen_rect: Rect,
+    painter: &Painter<R::Image>,
+    renderer: &mut R,
+) {
+    renderer.set_clip(None);
+    let meshes = primitives
+        .iter()
+        .filter_map(|primitive| match &primitive.primitive {
+            epaint::Primitive::Mesh(mesh) => Some((primitive.clip_rect, mesh)),
+            epaint::Primitive::Callback(_) => None,
+        });
+
+    for (_, mesh) in meshes.clone() {
+        let triangles = mesh.indices.chunks_exact(3).filter_map(|indices| {
+            let triangle = [0, 1, 2].map(|i| mesh.vertices.get(indices[i] as usize));
+            match triangle {
+                [Some(a), Some(b), Some(c)] => {
+                    Some([a, b, c].map(|v| vec2_from_egui(transform.apply(v.pos))))
+                }
+                _ => None,
+            }
+        });
+        if debug_paint.texture_colors {
+            let color = texture_color(mesh.texture_id);
+            for triangle in triangles.clone() {
+                renderer.fill_triangle(clockwise(triangle), color);
+            }
+        }
+        if debug_paint.wireframe {
+            let color = Color::from_rgba(0., 1., 1., 0.6);
+            for [a, b, c] in triangles {
+                line(renderer, a, b, color);
+                line(renderer, b, c, color);
+                line(renderer, c, a, color);
+            }
+        }
+    }
+
+    if debug_paint.clip_rects {
+        let color = Color::from_rgba(1., 0., 1., 0.8);
+        let mut drawn = vec![];
+        for (clip_rect, _) in meshes {
+            // unbounded clip rects can't go through the transform
+            let clip_rect = transform.apply_rect(clip_rect.intersect(screen_rect));
+            if drawn.contains(&clip_rect) || !clip_rect.is_positive() {
+                continue;
+            }
+            drawn.push(clip_rect);
+            outline(renderer, clip_rect, color);
+        }
+    }
+
+    if debug_paint.texture_panel {
+        texture_panel(painter, screen_rect, renderer);
+    }
+}
+
+/// Draws the textures of `painter` side by side at the bottom of the screen,
+/// each scaled to the panel height.
+fn texture_panel<R: Renderer + Canvas>(
+    painter: &Painter<R::Image>,
+    screen_rect: Rect,
+    renderer: &mut R,
+) {
+    let textures = painter.textures();
+    let height = PANEL_HEIGHT.min(screen_rect.height() / 2.);
+    let panel = Rect::from_min_max(
+        egui::pos2(
+            screen_rect.left(),
+            screen_rect.bottom() - height - 2. * PANEL_MARGIN,
+        ),
+        screen_rect.max,
+    );
+    renderer.fill_rectangle(rectangle(panel), Color::from_rgba(0.1, 0.1, 0.1, 0.9));
+
+    let mut x = panel.left() + PANEL_MARGIN;
+    for texture in textures {
+        let (width, texture_height) = (texture.size.0 as f32, texture.size.1 as f32);
+        if width == 0. || texture_height == 0. {
+            continue;
+        }
+        let scale = (height / texture_height).min(1.);
+        let rect = Rect::from_min_size(
+            egui::pos2(x, panel.top() + PANEL_MARGIN),
+            egui::vec2(width * scale, texture_height * scale),
+        );
+        if rect.left() >= panel.right() {
+            break;
+        }
+        // a background for the transparent parts of the texture
+        renderer.fill_rectangle(rectangle(rect), Color::from_gray(0.4));
+        image(renderer, rect, texture.handle);
+        outline(renderer, rect, texture_color(texture.id));
+        x = rect.right() + PANEL_MARGIN;
+    }
+}
+
+fn outline(canvas: &mut impl Canvas, rect: Rect, color: Color) {
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ]
+    .map(vec2_from_egui);
+    for i in 0..4 {
+        line(canvas, corners[i], corners[(i + 1) % 4], color);
+    }
+}
+
+/// Draws a line one pixel wide, as a quad of two triangles.
+fn line(canvas: &mut impl Canvas, start: Vec2, end: Vec2, color: Color) {
+    let direction = end - start;
+    let length = direction.magnitude();
+    if length == 0. || !length.is_finite() {
+        return;
+    }
+    let normal = Vec2::new(-direction.y, direction.x) * (0.5 / length);
+    let corners = [start + normal, end + normal, end - normal, start - normal];
+    canvas.fill_triangle(clockwise([corners[0], corners[1], corners[2]]), color);
+    canvas.fill_triangle(clockwise([corners[0], corners[2], corners[3]]), color);
+}
+
+/// Draws a whole image stretched over `rect`.
+fn image<R: Renderer>(renderer: &mut R, rect: Rect, image: &R::Image) {
+    let (min, max) = (vec2_from_egui(rect.min), vec2_from_egui(rect.max));
+    let (top_right, bottom_left) = (Vec2::new(max.x, min.y), Vec2::new(min.x, max.y));
+    let white = [Color::WHITE; 3];
+    renderer.draw_triangle(
+        [min, top_right, max],
+        white,
+        [Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.)],
+        image,
+    );
+    renderer.draw_triangle(
+        [min, max, bottom_left],
+        white,
+        [Vec2::new(0., 0.), Vec2::new(1., 1.), Vec2::new(0., 1.)],
+        image,
+    );
+}
+
+fn rectangle(rect: Rect) -> Rectangle {
+    Rectangle::new(vec2_from_egui(rect.min), vec2_from_egui(rect.max))
+}
+
+/// A translucent color for the texture `id`, the same every frame.
+fn texture_color(id: egui::TextureId) -> Color {
+    // hashed, so that consecutive ids get far apart hues
+    let hue = (Id::new(id).value() % 360) as f32 / 360.;
+    let color = egui::Color32::from(egui::epaint::Hsva::new(hue, 0.8, 1., 1.));
+    let color = color_from_egui(color);
+    Color::from_rgba(color.r(), color.g(), color.b(), 0.35)
+}
+
+/// Orders the vertices of a triangle clockwise, as speedy2d expects.
+fn clockwise(mut p: [Vec2; 3]) -> [Vec2; 3] {
+    let cross_product =
+        (p[1].x - p[0].x) * (p[2].y - p[0].y) - (p[1].y - p[0].y) * (p[2].x - p[0].x);
+    if cross_product.is_sign_positive() {
+        p.swap(1, 2);
+    }
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::Driver, ContextNamedExt, WindowHandler, WindowWrapper};
+    use speedy2d::window::WindowHelper;
+
+    /// A white square under the clip rect `(10.5, 10.5) - (70.5, 70.5)`, on
+    /// the main context or on a named one.
+    struct Square(Option<&'static str>);
+
+    impl WindowHandler for Square {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &Context,
+        ) {
+            let ctx = match self.0 {
+                Some(name) => egui_ctx.named_context(name).unwrap(),
+                None => egui_ctx.clone(),
+            };
+            ctx.tessellation_options_mut(|options| options.feathering = false);
+            ctx.set_debug_paint(DebugPaint {
+                clip_rects: true,
+                wireframe: true,
+                ..Default::default()
+            });
+            egui::Area::new("square".into())
+                .fixed_pos(egui::Pos2::ZERO)
+                .show(&ctx, |ui| {
+                    ui.set_clip_rect(Rect::from_min_max(
+                        egui::pos2(10.5, 10.5),
+                        egui::pos2(70.5, 70.5),
+                    ));
+                    let square = Rect::from_min_max(egui::pos2(30., 30.), egui::pos2(51., 51.));
+                    ui.painter().rect_filled(square, 0., egui::Color32::WHITE);
+                });
+        }
+    }
+
+    fn assert_outlined(name: Option<&'static str>) {
+        let mut window = WindowWrapper::headless(Square(name));
+        if let Some(name) = name {
+            window = window.with_context(name, 1);
+        }
+        let mut driver = Driver::new(window, (100, 100));
+        let snapshot = driver.run(2).clone();
+
+        // on the top edge of the clip rect, and just above it
+        let [r, g, b, _] = snapshot.pixel(40, 10);
+        assert!(r > 150 && g < 100 && b > 150, "{:?}", [r, g, b]);
+        assert_ne!(snapshot.pixel(40, 5), snapshot.pixel(40, 10));
+
+        // the center of the square is on the diagonal splitting it in two
+        // triangles, whichever it is
+        let [r, g, b, _] = snapshot.pixel(40, 40);
+        assert!(r < 150 && g > 200 && b > 200, "{:?}", [r, g, b]);
+        assert_eq!(snapshot.pixel(47, 35), [255; 4]);
+    }
+
+    #[test]
+    fn software_renderer_draws_the_clip_rects_and_the_wireframe() {
+        assert_outlined(None);
+    }
+
+    #[test]
+    fn named_contexts_are_outlined_too() {
+        assert_outlined(Some("hud"));
+    }
+}
@@ -0,0 +1,155 @@
+//! A window gathering egui's built-in debugging panels, for developers.
+
+use crate::{ContextDebugPaintExt, FrameStats, Painter};
+use egui::Context;
+use speedy2d::window::VirtualKeyCode;
+
+/// The developer tools window of a [`WindowWrapper`](crate::WindowWrapper),
+/// opened and closed with a key.
+pub(crate) struct DevTools {
+    pub(crate) key: VirtualKeyCode,
+    open: bool,
+}
+
+impl DevTools {
+    pub(crate) fn new(key: VirtualKeyCode) -> Self {
+        Self { key, open: false }
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Shows the window, if it is open.
+    pub(crate) fn show<I: Clone>(&mut self, ctx: &Context, painter: &Painter<I>) {
+        egui::Window::new("Dev tools")
+            .id(egui::Id::new("egui_speedy2d::dev_tools"))
+            .open(&mut self.open)
+            .default_size([360., 480.])
+            .vscroll(true)
+            .show(ctx, |ui| {
+                egui::CollapsingHeader::new("Settings").show(ui, |ui| ctx.settings_ui(ui));
+                egui::CollapsingHeader::new("Inspection").show(ui, |ui| ctx.inspection_ui(ui));
+                egui::CollapsingHeader::new("Memory").show(ui, |ui| memory_ui(ui, ctx));
+                egui::CollapsingHeader::new("egui textures").show(ui, |ui| ctx.texture_ui(ui));
+                egui::CollapsingHeader::new("Painter textures")
+                    .show(ui, |ui| texture_table(ui, painter));
+                egui::CollapsingHeader::new("Debug paint").show(ui, |ui| {
+                    let mut debug_paint = ctx.debug_paint();
+                    debug_paint.ui(ui);
+                    ctx.set_debug_paint(debug_paint);
+                });
+                if let Some(stats) = FrameStats::last(ctx) {
+                    egui::CollapsingHeader::new("Frame stats").show(ui, |ui| {
+                        ui.label(stats.to_string());
+                    });
+                }
+            });
+    }
+}
+
+/// egui's memory panel, without its "Reset all" button: it would also wipe
+/// the state this crate keeps in the data of the context, such as the slots
+/// of streaming textures, the pending context textures and the game view.
+fn memory_ui(ui: &mut egui::Ui, ctx: &Context) {
+    let (states, serialized) = ctx.data(|d| (d.len(), d.count_serialized()));
+    ui.label(format!(
+        "{states} widget states stored (of which {serialized} are serialized)."
+    ));
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} visible areas (panels, windows, popups, …)",
+            ctx.memory(|mem| mem.areas().visible_layer_ids().len())
+        ));
+        if ui.button("Reset").clicked() {
+            ctx.memory_mut(|mem| *mem.areas_mut() = Default::default());
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label(format!(
+            "{} scroll areas",
+            ctx.data(|d| d.count::<egui::scroll_area::State>())
+        ));
+        if ui.button("Reset").clicked() {
+            ctx.data_mut(|d| d.remove_by_type::<egui::scroll_area::State>());
+        }
+    });
+}
+
+/// Lists the textures the painter holds for the renderer.
+fn texture_table<I: Clone>(ui: &mut egui::Ui, painter: &Painter<I>) {
+    let stats = painter.texture_stats();
+    ui.label(format!(
+        "{} textures, {:.1} MiB GPU, {:.1} MiB CPU",
+        stats.count,
+        stats.gpu_bytes as f64 / (1 << 20) as f64,
+        stats.cpu_bytes as f64 / (1 << 20) as f64,
+    ));
+    egui::Grid::new("egui_speedy2d::dev_tools::textures")
+        .striped(true)
+        .num_columns(5)
+        .show(ui, |ui| {
+            for header in ["Id", "Name", "Size", "Filter", "CPU copy"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for texture in painter.textures() {
+                ui.label(format!("{:?}", texture.id));
+                ui.label(texture.name.unwrap_or(match texture.id {
+                    egui::TextureId::Managed(0) => "font atlas",
+                    _ => "",
+                }));
+                ui.label(format!("{} x {}", texture.size.0, texture.size.1));
+                ui.label(format!("{:?}", texture.options.magnification));
+                ui.label(if texture.cpu_copy { "yes" } else { "no" });
+                ui.end_row();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::Driver, Canvas, WindowHandler, WindowWrapper};
+    use speedy2d::window::{VirtualKeyCode, WindowHelper};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Records the keys pressed as egui sees them.
+    struct Keys(Rc<RefCell<Vec<egui::Key>>>);
+
+    impl WindowHandler for Keys {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &egui::Context,
+        ) {
+            egui_ctx.input(|i| {
+                for event in &i.events {
+                    if let egui::Event::Key {
+                        key, pressed: true, ..
+                    } = event
+                    {
+                        self.0.borrow_mut().push(*key);
+                    }
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn the_dev_tools_key_toggles_them_without_reaching_egui() {
+        let keys = Rc::new(RefCell::new(vec![]));
+        let window = WindowWrapper::headless(Keys(keys.clone())).with_dev_tools(true);
+        let mut driver = Driver::new(window, (400, 300));
+        let is_open = |driver: &Driver| driver.window().dev_tools.as_ref().unwrap().open;
+        let closed = driver.snapshot().clone();
+
+        driver.press_key(VirtualKeyCode::F12);
+        assert!(is_open(&driver));
+        assert_ne!(driver.snapshot(), &closed);
+        driver.press_key(VirtualKeyCode::F12);
+        assert!(!is_open(&driver));
+        driver.press_key(VirtualKeyCode::A);
+        assert_eq!(*keys.borrow(), [egui::Key::A]);
+    }
+}
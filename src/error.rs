@@ -0,0 +1,102 @@
+//! The problems reported while drawing the egui UI.
+
+use std::{fmt, io, path::PathBuf};
+
+/// A problem encountered while drawing the egui UI.
+///
+/// None of these stop the frame. They are reported through
+/// [`WindowHandler::on_error`](crate::WindowHandler::on_error).
+#[derive(Debug)]
+pub enum WindowWrapperError {
+    /// The renderer failed to create the image backing a texture.
+    TextureUpload {
+        texture_id: egui::TextureId,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A mesh was drawn with a texture that doesn't exist. It was drawn with
+    /// a fallback texture instead.
+    ///
+    /// Reported once for each texture, until a mesh is drawn with it again
+    /// after it was created.
+    MissingTexture(egui::TextureId),
+    /// The renderer failed to create the checkerboard drawn in place of
+    /// missing textures, so the meshes using them were skipped.
+    FallbackTexture(Box<dyn std::error::Error + Send + Sync>),
+    /// egui sent a partial update for a texture that has no CPU copy to
+    /// apply it to. See [`CpuCopyPolicy`](crate::CpuCopyPolicy).
+    ///
+    /// Reported once for each texture, until egui uploads it whole again.
+    PartialUpdateWithoutCpuCopy(egui::TextureId),
+    /// A mesh had indices that don't form triangles or point past its
+    /// vertices. The valid triangles were still drawn.
+    InvalidMesh(egui::TextureId),
+    /// A paint callback was skipped, renderers don't support them.
+    UnsupportedPaintCallback,
+    /// [`Painter::set_user_texture`](crate::Painter::set_user_texture) was
+    /// given an id that wasn't handed out by the painter, and isn't in
+    /// [`CHOSEN_USER_TEXTURE_IDS`](crate::CHOSEN_USER_TEXTURE_IDS) either.
+    ReservedTextureId(u64),
+    /// The pixels given for a texture aren't `width * height * 4` bytes long,
+    /// or that length doesn't fit in memory.
+    TexturePixels { size: [usize; 2], len: usize },
+    /// A screenshot requested with
+    /// [`ContextScreenshotExt::save_screenshot`](crate::ContextScreenshotExt::save_screenshot)
+    /// couldn't be written.
+    Screenshot { path: PathBuf, source: io::Error },
+}
+
+impl fmt::Display for WindowWrapperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TextureUpload { texture_id, source } => {
+                write!(f, "failed to upload texture {texture_id:?}: {source}")
+            }
+            Self::MissingTexture(texture_id) => {
+                write!(f, "mesh uses missing texture {texture_id:?}")
+            }
+            Self::FallbackTexture(source) => {
+                write!(f, "failed to create the fallback texture: {source}")
+            }
+            Self::PartialUpdateWithoutCpuCopy(texture_id) => write!(
+                f,
+                "partial updates of texture {texture_id:?} dropped, no CPU copy was kept"
+            ),
+            Self::InvalidMesh(texture_id) => {
+                write!(f, "mesh with texture {texture_id:?} has invalid indices")
+            }
+            Self::UnsupportedPaintCallback => write!(f, "paint callbacks are not supported"),
+            Self::ReservedTextureId(id) => write!(
+                f,
+                "user texture id {id} is reserved, pick one in {:?}",
+                crate::CHOSEN_USER_TEXTURE_IDS
+            ),
+            Self::TexturePixels { size, len } => match crate::painter::rgba_len(*size) {
+                Some(expected) => write!(
+                    f,
+                    "expected {expected} bytes of RGBA pixels for a {}x{} texture, got {len}",
+                    size[0], size[1]
+                ),
+                None => write!(
+                    f,
+                    "a {}x{} texture is too large, got {len} bytes of RGBA pixels",
+                    size[0], size[1]
+                ),
+            },
+            Self::Screenshot { path, source } => {
+                write!(f, "failed to write screenshot {}: {source}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for WindowWrapperError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::TextureUpload { source, .. } | Self::FallbackTexture(source) => {
+                Some(source.as_ref())
+            }
+            Self::Screenshot { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Showing the game inside an egui widget, for editor layouts where the game
+//! sits in a panel surrounded by tools.
+//!
+//! The pixels of the game view come from a [`GameViewSource`], set with
+//! [`ContextGameViewExt::set_game_view`]. They are uploaded as a user texture
+//! before the egui frame is painted, and shown with a [`GameView`] widget, or
+//! with `ui.image` and [`ContextGameViewExt::game_view_texture`].
+
+use crate::{Painter, Renderer, WindowWrapperError};
+use egui::{
+    load::SizedTexture, Color32, ColorImage, Context, Id, Pos2, Rect, Response, Sense, Ui, Vec2,
+};
+use speedy2d::shape::URect;
+use std::sync::Arc;
+
+/// Where the pixels of the game view come from.
+#[derive(Clone, Debug)]
+pub enum GameViewSource {
+    /// What the handler drew in
+    /// [`on_draw_before_ui`](crate::WindowHandler::on_draw_before_ui), read
+    /// back from the window every frame. `None` captures the whole window,
+    /// otherwise only the given region, in physical pixels.
+    ///
+    /// The game's drawing stays on the window under the UI: clear the screen
+    /// at the start of [`on_draw`](crate::WindowHandler::on_draw) if the game
+    /// view doesn't cover it. Reading back the window stalls the GPU, prefer
+    /// [`GameViewSource::Pixels`] when the game renders to memory anyway.
+    Capture(Option<URect>),
+    /// A pixel buffer, uploaded once each time it is set.
+    Pixels(Arc<ColorImage>),
+}
+
+/// Access to the game view of an egui context.
+pub trait ContextGameViewExt {
+    /// Sets where the pixels of the game view come from, and how its
+    /// texture is sampled.
+    fn set_game_view(&self, source: GameViewSource, options: egui::TextureOptions);
+
+    /// Returns the texture holding the game view, and its size in pixels,
+    /// once pixels have been given or captured and uploaded.
+    fn game_view_texture(&self) -> Option<SizedTexture>;
+}
+
+impl ContextGameViewExt for Context {
+    fn set_game_view(&self, source: GameViewSource, options: egui::TextureOptions) {
+        self.data_mut(|d| {
+            let state = d.get_temp_mut_or_default::<GameViewState>(state_id());
+            if let GameViewSource::Pixels(image) = &source {
+                state.image = Some(image.clone());
+                state.dirty = true;
+            }
+            state.dirty |= state.options != options;
+            state.source = Some(source);
+            state.options = options;
+        });
+    }
+
+    fn game_view_texture(&self) -> Option<SizedTexture> {
+        self.data(|d| d.get_temp::<GameViewState>(state_id())?.texture)
+    }
+}
+
+#[derive(Clone, Default)]
+struct GameViewState {
+    source: Option<GameViewSource>,
+    options: egui::TextureOptions,
+    image: Option<Arc<ColorImage>>,
+    /// Whether `image` changed since it was last uploaded.
+    dirty: bool,
+    /// The texture `image` was last uploaded to, allocated by the painter on
+    /// the first upload.
+    texture: Option<SizedTexture>,
+}
+
+fn state_id() -> Id {
+    Id::new("egui_speedy2d::game_view")
+}
+
+/// Returns the region to capture, if the game view is captured from the
+/// window.
+pub(crate) fn capture_region(ctx: &Context) -> Option<Option<URect>> {
+    ctx.data(|d| match d.get_temp::<GameViewState>(state_id())?.source {
+        Some(GameViewSource::Capture(region)) => Some(region),
+        _ => None,
+    })
+}
+
+/// Sets the game view to a region of `window`, as returned by
+/// [`capture_region`].
+pub(crate) fn set_captured(ctx: &Context, window: &ColorImage, region: Option<URect>) {
+    let image = match region {
+        None => window.clone(),
+        Some(region) => {
+            let [width, height] = window.size.map(|v| v as f32);
+            let min = egui::pos2(region.top_left().x as f32, region.top_left().y as f32)
+                .clamp(Pos2::ZERO, egui::pos2(width, height));
+            let max = egui::pos2(
+                region.bottom_right().x as f32,
+                region.bottom_right().y as f32,
+            )
+            .clamp(min, egui::pos2(width, height));
+            window.region(&Rect::from_min_max(min, max), None)
+        }
+    };
+    ctx.data_mut(|d| {
+        let state = d.get_temp_mut_or_default::<GameViewState>(state_id());
+        state.image = Some(Arc::new(image));
+        state.dirty = true;
+    });
+}
+
+/// Uploads the game view pixels if they changed since they were last
+/// uploaded.
+pub(crate) fn upload<R: Renderer>(
+    ctx: &Context,
+    painter: &mut Painter<R::Image>,
+    renderer: &mut R,
+) -> Result<(), WindowWrapperError> {
+    let Some((image, options, texture)) = ctx.data(|d| {
+        let state = d.get_temp::<GameViewState>(state_id())?;
+        let image = state.image.filter(|_| state.dirty)?;
+        Some((image, state.options, state.texture))
+    }) else {
+        return Ok(());
+    };
+    let size = [image.size[0] as f32, image.size[1] as f32];
+    let id = match texture.map(|texture| texture.id) {
+        Some(egui::TextureId::User(id)) => {
+            painter.set_user_texture(renderer, id, image.clone(), options)?
+        }
+        _ => {
+            // the UI of this frame was built without the texture
+            ctx.request_repaint();
+            painter.add_user_texture(renderer, image.clone(), options)?
+        }
+    };
+    ctx.data_mut(|d| {
+        let state = d.get_temp_mut_or_default::<GameViewState>(state_id());
+        state.texture = Some(SizedTexture::new(id, size));
+        // a failed upload leaves it dirty, to be tried again next frame
+        if state.image.as_ref().is_some_and(|i| Arc::ptr_eq(i, &image)) {
+            state.dirty = false;
+        }
+    });
+    Ok(())
+}
+
+/// Shows the game view, scaled to fit the available space while keeping its
+/// aspect ratio, with bars filling the rest.
+///
+/// ```
+/// use egui_speedy2d::{testing::Harness, ContextGameViewExt, GameView, GameViewSource};
+/// use std::sync::Arc;
+///
+/// // a 4x2 red game, shown in a 200x200 panel
+/// let game = egui::ColorImage::new([4, 2], egui::Color32::RED);
+/// let mut harness = Harness::new((200, 200));
+/// harness.on_frame(1, [egui::Event::PointerMoved(egui::pos2(150., 75.))]);
+/// let mut hovered = None;
+/// // the texture is allocated when the first frame is drawn
+/// let snapshot = harness.run(3, |ctx| {
+///     ctx.set_game_view(GameViewSource::Pixels(Arc::new(game.clone())), Default::default());
+///     egui::CentralPanel::default()
+///         .frame(egui::Frame::none())
+///         .show(ctx, |ui| hovered = GameView::new().show(ui).hover_pos());
+/// });
+///
+/// // letterboxed between y = 50 and y = 150
+/// assert_eq!(snapshot.pixel(100, 20), [0, 0, 0, 255]);
+/// assert_eq!(snapshot.pixel(100, 100), [255, 0, 0, 255]);
+/// assert_eq!(hovered, Some(egui::pos2(3., 0.5)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct GameView {
+    background: Color32,
+    sense: Sense,
+}
+
+impl Default for GameView {
+    fn default() -> Self {
+        Self {
+            background: Color32::BLACK,
+            sense: Sense::click_and_drag(),
+        }
+    }
+}
+
+impl GameView {
+    /// Creates a game view with black bars, sensing clicks and drags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the color of the bars around the game. Defaults to black.
+    pub fn background(mut self, color: Color32) -> Self {
+        self.background = color;
+        self
+    }
+
+    /// Sets how the game view reacts to the pointer. Defaults to clicks and
+    /// drags.
+    pub fn sense(mut self, sense: Sense) -> Self {
+        self.sense = sense;
+        self
+    }
+
+    /// Fills the available space with the game view.
+    pub fn show(self, ui: &mut Ui) -> GameViewResponse {
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+        ui.painter().rect_filled(rect, 0., self.background);
+
+        let texture = ui.ctx().game_view_texture();
+        let game_size = texture.map_or(Vec2::ZERO, |t| t.size);
+        let image_rect = letterbox(rect, game_size);
+        if let Some(texture) = texture {
+            let uv = Rect::from_min_max(Pos2::ZERO, egui::pos2(1., 1.));
+            ui.painter()
+                .image(texture.id, image_rect, uv, Color32::WHITE);
+        }
+        let response = ui.interact(image_rect, response.id.with("image"), self.sense);
+        GameViewResponse {
+            response,
+            image_rect,
+            game_size,
+        }
+    }
+}
+
+/// Returns the largest rect with the aspect ratio of `size` fitting in
+/// `rect`, centered in it.
+fn letterbox(rect: Rect, size: Vec2) -> Rect {
+    if size.x <= 0. || size.y <= 0. {
+        return Rect::from_center_size(rect.center(), Vec2::ZERO);
+    }
+    let scale = (rect.width() / size.x).min(rect.height() / size.y);
+    Rect::from_center_size(rect.center(), size * scale)
+}
+
+/// What happened to a [`GameView`], with pointer positions mapped to game
+/// pixels.
+pub struct GameViewResponse {
+    /// The response of the game image, without the bars around it.
+    pub response: Response,
+    /// Where the game image was drawn, in points.
+    pub image_rect: Rect,
+    /// Size of the game in pixels, zero until the game view has pixels.
+    pub game_size: Vec2,
+}
+
+impl GameViewResponse {
+    /// Maps a position in points to game pixels. Positions outside the image
+    /// map outside the game.
+    pub fn to_game(&self, pos: Pos2) -> Pos2 {
+        Pos2::ZERO + (pos - self.image_rect.min) / self.scale()
+    }
+
+    /// Maps a distance in points to game pixels.
+    pub fn to_game_vec(&self, delta: Vec2) -> Vec2 {
+        delta / self.scale()
+    }
+
+    /// Returns where the pointer hovers the game, in game pixels.
+    pub fn hover_pos(&self) -> Option<Pos2> {
+        self.response.hover_pos().map(|pos| self.to_game(pos))
+    }
+
+    /// Returns where the game is being clicked or dragged, in game pixels.
+    pub fn interact_pos(&self) -> Option<Pos2> {
+        self.response
+            .interact_pointer_pos()
+            .map(|pos| self.to_game(pos))
+    }
+
+    /// Returns how far the game was dragged this frame, in game pixels.
+    pub fn drag_delta(&self) -> Vec2 {
+        self.to_game_vec(self.response.drag_delta())
+    }
+
+    /// Points per game pixel.
+    fn scale(&self) -> f32 {
+        if self.game_size.x > 0. {
+            self.image_rect.width() / self.game_size.x
+        } else {
+            1.
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SoftwareImage, SoftwareRenderer};
+    use speedy2d::{
+        color::Color,
+        dimen::{UVec2, Vec2 as SpeedyVec2},
+        image::ImageSmoothingMode,
+        shape::Rectangle,
+    };
+
+    /// A software renderer failing to create images while `fail` is set.
+    struct FlakyRenderer {
+        renderer: SoftwareRenderer,
+        fail: bool,
+    }
+
+    impl Renderer for FlakyRenderer {
+        type Image = SoftwareImage;
+
+        fn create_image(
+            &mut self,
+            size: UVec2,
+            smoothing_mode: ImageSmoothingMode,
+            pixels: &[u8],
+        ) -> Result<Self::Image, Box<dyn std::error::Error + Send + Sync>> {
+            if self.fail {
+                return Err("out of memory".into());
+            }
+            self.renderer.create_image(size, smoothing_mode, pixels)
+        }
+
+        fn set_clip(&mut self, rect: Option<Rectangle<i32>>) {
+            self.renderer.set_clip(rect)
+        }
+
+        fn draw_triangle(
+            &mut self,
+            positions: [SpeedyVec2; 3],
+            colors: [Color; 3],
+            uvs: [SpeedyVec2; 3],
+            image: &Self::Image,
+        ) {
+            self.renderer.draw_triangle(positions, colors, uvs, image)
+        }
+    }
+
+    #[test]
+    fn failed_uploads_are_tried_again() {
+        let ctx = Context::default();
+        let mut painter = Painter::default();
+        let mut renderer = FlakyRenderer {
+            renderer: SoftwareRenderer::new((1, 1)),
+            fail: true,
+        };
+        let game = ColorImage::new([1, 1], Color32::RED);
+        ctx.set_game_view(GameViewSource::Pixels(Arc::new(game)), Default::default());
+
+        assert!(upload(&ctx, &mut painter, &mut renderer).is_err());
+        assert!(ctx.game_view_texture().is_none());
+
+        // uploaded on the next frame, without new pixels
+        renderer.fail = false;
+        assert!(upload(&ctx, &mut painter, &mut renderer).is_ok());
+        assert!(ctx.game_view_texture().is_some());
+    }
+}
@@ -0,0 +1,347 @@
+//! An egui texture loader decoding PNG and JPEG images, so that
+//! `egui::Image::new("file://...")` works out of the box.
+
+use crate::{Painter, Renderer, WindowWrapperError};
+use egui::{
+    load::{BytesPoll, LoadError, SizeHint, SizedTexture, TextureLoadResult, TexturePoll},
+    mutex::Mutex,
+    ColorImage, Context, TextureOptions,
+};
+use std::collections::HashMap;
+
+/// Decodes PNG and JPEG images and uploads them as user textures.
+///
+/// [`WindowWrapper`](crate::WindowWrapper) installs one in its egui context
+/// when the `image_loader` feature is enabled. `file://` URIs are read from
+/// disk, other URIs are fetched through egui's bytes loaders, such as
+/// `bytes://` for images passed with [`egui::include_image`].
+///
+/// Images are decoded on the UI thread the first time they are asked for, and
+/// uploaded when the frame is drawn. Failures are reported as
+/// [`LoadError::Loading`], and cached until the URI is forgotten with
+/// [`egui::Context::forget_image`].
+///
+/// ```
+/// use egui::load::{LoadError, SizeHint, TexturePoll};
+/// use egui_speedy2d::testing::{Harness, Snapshot};
+///
+/// let path = std::env::temp_dir().join("egui_speedy2d_image_loader.png");
+/// let red = Snapshot { size: [2, 2], pixels: [255, 0, 0, 255].repeat(4) };
+/// red.save_png(&path).unwrap();
+/// let uri = format!("file://{}", path.display());
+///
+/// let mut harness = Harness::new((100, 100));
+/// let snapshot = harness.run(3, |ctx| {
+///     egui::CentralPanel::default()
+///         .frame(egui::Frame::none())
+///         .show(ctx, |ui| {
+///             ui.add(egui::Image::new(&uri).fit_to_exact_size(egui::vec2(40., 40.)))
+///         });
+/// });
+/// assert_eq!(snapshot.pixel(20, 20), [255, 0, 0, 255]);
+///
+/// let ctx = harness.ctx();
+/// let texture = ctx.try_load_texture(&uri, Default::default(), SizeHint::default());
+/// assert!(matches!(texture, Ok(TexturePoll::Ready { .. })));
+/// let missing = ctx.try_load_texture("file://missing.png", Default::default(), SizeHint::default());
+/// assert!(matches!(missing, Err(LoadError::Loading(_))));
+/// ```
+pub struct ImageLoader {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<(String, TextureOptions), Entry>,
+    max_bytes: Option<usize>,
+    to_free: Vec<u64>,
+}
+
+enum Entry {
+    /// Waiting to be uploaded.
+    Decoded(ColorImage),
+    Ready {
+        texture: SizedTexture,
+        /// Frame the texture was last asked for.
+        last_used: u64,
+    },
+    Failed(String),
+}
+
+impl Entry {
+    fn byte_size(&self) -> usize {
+        match self {
+            Self::Decoded(image) => image.pixels.len() * 4,
+            Self::Ready { texture, .. } => texture.size.x as usize * texture.size.y as usize * 4,
+            Self::Failed(_) => 0,
+        }
+    }
+}
+
+impl Default for ImageLoader {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(Default::default()),
+        }
+    }
+}
+
+impl ImageLoader {
+    pub const ID: &'static str = concat!(module_path!(), "::ImageLoader");
+
+    /// Limits the memory used by loaded images. Past the limit, the images
+    /// that were not asked for during the frame are evicted, least recently
+    /// used first, and loaded again if they are asked for later.
+    ///
+    /// Defaults to no limit.
+    pub fn set_max_bytes(&self, max_bytes: Option<usize>) {
+        self.state.lock().max_bytes = max_bytes;
+    }
+
+    /// Uploads the images decoded since the last call, frees the forgotten
+    /// ones and evicts images to stay under the memory limit.
+    pub(crate) fn upload<R: Renderer>(
+        &self,
+        ctx: &Context,
+        painter: &mut Painter<R::Image>,
+        renderer: &mut R,
+    ) -> Vec<WindowWrapperError> {
+        let mut errors = vec![];
+        let mut state = self.state.lock();
+        // called once the frame is over, after `end_frame` counted it: the
+        // images shown in the frame being drawn are stamped with the one before
+        let frame = ctx.frame_nr().saturating_sub(1);
+
+        for ((_, options), entry) in &mut state.entries {
+            let Entry::Decoded(image) = entry else {
+                continue;
+            };
+            let size = egui::vec2(image.size[0] as f32, image.size[1] as f32);
+            let image = std::mem::take(image);
+            *entry = match painter.add_user_texture(renderer, image, *options) {
+                Ok(id) => Entry::Ready {
+                    texture: SizedTexture::new(id, size),
+                    last_used: frame,
+                },
+                Err(error) => {
+                    let message = error.to_string();
+                    errors.push(error);
+                    Entry::Failed(message)
+                }
+            };
+        }
+
+        if let Some(max_bytes) = state.max_bytes {
+            let mut byte_size = state.entries.values().map(Entry::byte_size).sum::<usize>();
+            let mut unused = state
+                .entries
+                .iter()
+                .filter_map(|(key, entry)| match entry {
+                    Entry::Ready { last_used, .. } if *last_used < frame => {
+                        Some((*last_used, key.clone()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            unused.sort_by_key(|(last_used, _)| *last_used);
+            for (_, key) in unused {
+                if byte_size <= max_bytes {
+                    break;
+                }
+                if let Some(entry) = state.entries.remove(&key) {
+                    byte_size -= entry.byte_size();
+                    free(&mut state.to_free, entry);
+                }
+            }
+        }
+
+        for id in state.to_free.drain(..) {
+            painter.free_user_texture(id);
+        }
+        errors
+    }
+}
+
+/// Queues the texture of an evicted entry to be freed.
+fn free(to_free: &mut Vec<u64>, entry: Entry) {
+    if let Entry::Ready { texture, .. } = entry {
+        if let egui::TextureId::User(id) = texture.id {
+            to_free.push(id);
+        }
+    }
+}
+
+impl egui::load::TextureLoader for ImageLoader {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn load(
+        &self,
+        ctx: &Context,
+        uri: &str,
+        texture_options: TextureOptions,
+        _size_hint: SizeHint,
+    ) -> TextureLoadResult {
+        let key = (uri.to_owned(), texture_options);
+        match self.state.lock().entries.get_mut(&key) {
+            Some(Entry::Ready { texture, last_used }) => {
+                *last_used = ctx.frame_nr();
+                return Ok(TexturePoll::Ready { texture: *texture });
+            }
+            Some(Entry::Decoded(image)) => {
+                let size = egui::vec2(image.size[0] as f32, image.size[1] as f32);
+                return Ok(TexturePoll::Pending { size: Some(size) });
+            }
+            Some(Entry::Failed(message)) => return Err(LoadError::Loading(message.clone())),
+            None => {}
+        }
+
+        let decoded = if let Some(path) = uri.strip_prefix("file://") {
+            let extension = path.rsplit('.').next().unwrap_or_default();
+            if !["png", "jpg", "jpeg"].contains(&extension.to_ascii_lowercase().as_str()) {
+                return Err(LoadError::NotSupported);
+            }
+            match std::fs::read(path) {
+                Ok(bytes) => match decode(&bytes) {
+                    Some(decoded) => decoded,
+                    None => return Err(LoadError::NotSupported),
+                },
+                Err(error) => Err(error.to_string()),
+            }
+        } else {
+            match ctx.try_load_bytes(uri) {
+                Ok(BytesPoll::Ready { bytes, .. }) => match decode(&bytes) {
+                    Some(decoded) => decoded,
+                    None => return Err(LoadError::NotSupported),
+                },
+                Ok(BytesPoll::Pending { size }) => return Ok(TexturePoll::Pending { size }),
+                Err(LoadError::NoMatchingBytesLoader) => return Err(LoadError::NotSupported),
+                Err(error) => return Err(error),
+            }
+        };
+
+        let (entry, result) = match decoded {
+            Ok(image) => {
+                let size = egui::vec2(image.size[0] as f32, image.size[1] as f32);
+                let pending = TexturePoll::Pending { size: Some(size) };
+                (Entry::Decoded(image), Ok(pending))
+            }
+            Err(message) => {
+                let message = format!("{uri}: {message}");
+                (
+                    Entry::Failed(message.clone()),
+                    Err(LoadError::Loading(message)),
+                )
+            }
+        };
+        self.state.lock().entries.insert(key, entry);
+        ctx.request_repaint();
+        result
+    }
+
+    fn forget(&self, uri: &str) {
+        let mut state = self.state.lock();
+        let State {
+            entries, to_free, ..
+        } = &mut *state;
+        let keys = entries
+            .keys()
+            .filter(|(key, _)| key == uri)
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            if let Some(entry) = entries.remove(&key) {
+                free(to_free, entry);
+            }
+        }
+    }
+
+    fn forget_all(&self) {
+        let mut state = self.state.lock();
+        let State {
+            entries, to_free, ..
+        } = &mut *state;
+        for (_, entry) in entries.drain() {
+            free(to_free, entry);
+        }
+    }
+
+    fn byte_size(&self) -> usize {
+        self.state
+            .lock()
+            .entries
+            .values()
+            .map(Entry::byte_size)
+            .sum()
+    }
+}
+
+/// Decodes a PNG or JPEG image, or returns `None` for other formats.
+fn decode(bytes: &[u8]) -> Option<Result<ColorImage, String>> {
+    match image::guess_format(bytes) {
+        Ok(image::ImageFormat::Png | image::ImageFormat::Jpeg) => {}
+        _ => return None,
+    }
+    Some(
+        image::load_from_memory(bytes)
+            .map(|image| {
+                let image = image.to_rgba8();
+                let size = [image.width() as usize, image.height() as usize];
+                ColorImage::from_rgba_unmultiplied(size, image.as_raw())
+            })
+            .map_err(|error| error.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SoftwareRenderer;
+    use std::sync::Arc;
+
+    fn is_loaded(loader: &ImageLoader, uri: &str) -> bool {
+        let key = (uri.to_owned(), TextureOptions::default());
+        matches!(
+            loader.state.lock().entries.get(&key),
+            Some(Entry::Ready { .. })
+        )
+    }
+
+    #[test]
+    fn keeps_the_images_shown_in_the_drawn_frame() {
+        let ctx = Context::default();
+        let loader = Arc::new(ImageLoader::default());
+        ctx.add_texture_loader(loader.clone());
+        let png = crate::png_io::encode([4, 4], &[255; 4 * 4 * 4]).unwrap();
+        ctx.include_bytes("bytes://a.png", png.clone());
+        ctx.include_bytes("bytes://b.png", png);
+        // room for a single 4x4 image
+        loader.set_max_bytes(Some(4 * 4 * 4));
+
+        let mut painter = Painter::default();
+        let mut renderer = SoftwareRenderer::new((8, 8));
+        let mut frame = |uris: &[&str]| {
+            let _ = ctx.run(Default::default(), |ctx| {
+                for uri in uris {
+                    let _ = ctx.try_load_texture(uri, Default::default(), SizeHint::default());
+                }
+            });
+            let errors = loader.upload(&ctx, &mut painter, &mut renderer);
+            assert!(errors.is_empty(), "{errors:?}");
+        };
+
+        frame(&["bytes://a.png", "bytes://b.png"]);
+        assert!(is_loaded(&loader, "bytes://a.png"));
+        assert!(is_loaded(&loader, "bytes://b.png"));
+
+        frame(&["bytes://a.png"]);
+        assert!(is_loaded(&loader, "bytes://a.png"));
+        assert!(!is_loaded(&loader, "bytes://b.png"));
+
+        // images on screen are kept even over the limit
+        loader.set_max_bytes(Some(0));
+        frame(&["bytes://a.png"]);
+        assert!(is_loaded(&loader, "bytes://a.png"));
+    }
+}
@@ -13,16 +13,18 @@
 //! rendering.
 //!
 //! ```
+//! # use egui_speedy2d::Canvas;
+//! # use speedy2d::{color::Color, window::WindowHelper};
 //! struct MyWindowHandler;
 //!
 //! impl egui_speedy2d::WindowHandler for MyWindowHandler {
 //!     fn on_draw(
 //!         &mut self,
-//!         helper: &mut WindowHelper,
-//!         graphics: &mut Graphics2D,
+//!         helper: Option<&mut WindowHelper>,
+//!         canvas: &mut dyn Canvas,
 //!         egui_ctx: &egui::Context,
 //!     ) {
-//!         graphics.clear_screen(Color::WHITE);
+//!         canvas.clear_screen(Color::WHITE);
 //!         egui::Window::new("Hello").show(&egui_ctx, |ui| {
 //!             ui.label("World !");
 //!         });
@@ -35,174 +37,843 @@
 //! [`speedy2d::windows::WindowHandler` trait](speedy2d::window::WindowHandler).
 //!
 //! ```no_run
-//! fn main() {
-//!     let window = speedy2d::Window::new_centered("Speedy2D: Hello World", (640, 240)).unwrap();
-//!     window.run_loop(egui_speedy2d::WindowWrapper::new(MyWindowHandler{}))
-//! }
+//! # struct MyWindowHandler;
+//! # impl egui_speedy2d::WindowHandler for MyWindowHandler {}
+//! let window = speedy2d::Window::new_centered("Speedy2D: Hello World", (640, 240)).unwrap();
+//! window.run_loop(egui_speedy2d::WindowWrapper::new(MyWindowHandler{}))
 //! ```
+//!
+//! ## World-space UI
+//!
+//! Everything an egui context paints can be moved, scaled and rotated with a
+//! [`Transform`], set from any callback through [`ContextTransformExt`]. Pointer
+//! events are mapped back through the inverse transform so widgets stay
+//! interactive.
+
+#[cfg(feature = "accesskit")]
+pub mod accessibility;
+#[cfg(feature = "mirror")]
+pub mod mirror;
+#[cfg(feature = "record")]
+pub mod record;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod testing;
+
+mod contexts;
+mod cursor;
+mod debug_paint;
+mod dev_tools;
+mod error;
+mod game_view;
+#[cfg(feature = "image_loader")]
+mod image_loader;
+mod painter;
+mod png_io;
+mod profiling;
+mod renderer;
+mod screenshot;
+mod software;
+mod stats;
+mod streaming;
+mod svg;
+mod textures;
+mod transform;
+mod wrap;
 
+pub use contexts::ContextNamedExt;
+pub use debug_paint::{ContextDebugPaintExt, DebugPaint};
 pub use egui;
+pub use error::WindowWrapperError;
+pub use game_view::{ContextGameViewExt, GameView, GameViewResponse, GameViewSource};
+#[cfg(feature = "image_loader")]
+pub use image_loader::ImageLoader;
+pub use painter::{CpuCopyPolicy, Painter, TextureStats, CHOSEN_USER_TEXTURE_IDS};
+pub use renderer::{Canvas, Renderer};
+pub use screenshot::ContextScreenshotExt;
+pub use software::{SoftwareImage, SoftwareRenderer};
+pub use stats::FrameStats;
+pub use streaming::StreamingTexture;
+pub use svg::{SvgImage, SvgRenderer};
+pub use textures::ContextTexturesExt;
+pub use transform::{ContextTransformExt, Transform};
+
 use egui::{Context, RawInput};
+use profiling::profile_scope;
+use renderer::WindowRenderer;
 use speedy2d::{
     color::Color,
     dimen::{UVec2, Vec2},
-    error::{BacktraceError, ErrorMessage},
-    image::{ImageDataType, ImageHandle, ImageSmoothingMode},
+    image::{ImageHandle, ImageSmoothingMode},
     window::{
         KeyScancode, ModifiersState, MouseButton, MouseScrollDistance, VirtualKeyCode,
         WindowHelper, WindowStartupInfo,
     },
     Graphics2D,
 };
-use std::collections::HashMap;
+#[cfg(feature = "image_loader")]
+use std::sync::Arc;
+use std::time::Instant;
 
 /// Wraps an egui context with features that are useful
 /// for integrating egui with Speedy2d.
-pub struct WindowWrapper<UserEventType> {
+///
+/// `I` is the type of the images the UI is drawn with: speedy2d's for a
+/// window, or [`SoftwareImage`] for a wrapper created with
+/// [`WindowWrapper::headless`].
+pub struct WindowWrapper<UserEventType, I = ImageHandle> {
     handler: Box<dyn WindowHandler<UserEventType>>,
     raw_input: RawInput,
     egui_ctx: Context,
-    id_and_textures: HashMap<u64, (ImageHandle, RgbaImage)>,
-    to_free_textures: Vec<u64>,
+    painter: Painter<I>,
     last_mouse_position: Vec2,
     current_modifiers: ModifiersState,
+    software_cursor: bool,
+    os_cursor_hidden: bool,
+    frame_stats: FrameStats,
+    last_frame_start: Option<Instant>,
+    stats_overlay: Option<stats::StatsOverlay>,
+    dev_tools: Option<dev_tools::DevTools>,
+    contexts: Vec<contexts::NamedContext<I>>,
+    #[cfg(feature = "image_loader")]
+    image_loader: Arc<ImageLoader>,
+    #[cfg(feature = "accesskit")]
+    tree_consumer: Option<Box<dyn accessibility::TreeConsumer>>,
+    #[cfg(feature = "accesskit")]
+    accesskit_actions: accessibility::ActionQueue,
+    #[cfg(feature = "record")]
+    input_recorder: Option<record::InputRecorder>,
+    #[cfg(feature = "record")]
+    input_replay: Option<record::InputReplay>,
+    #[cfg(feature = "remote")]
+    remote_server: Option<remote::RemoteServer>,
+    #[cfg(feature = "mirror")]
+    mirror_server: Option<mirror::MirrorServer>,
 }
 
 impl<UserEventType> WindowWrapper<UserEventType> {
     /// Creates a new [`WindowWrapper`] and underlying egui context.
     pub fn new(handler: impl WindowHandler<UserEventType> + 'static) -> Self {
+        Self::with_handler(Box::new(handler))
+    }
+
+    /// Returns a texture id to show a speedy2d image in egui, for example
+    /// with `ui.image((texture_id, size))`.
+    ///
+    /// The same as [`ContextTexturesExt::texture_id_for`] on the main context,
+    /// which can be called from the handler.
+    pub fn texture_id_for(
+        &self,
+        image: &ImageHandle,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        self.egui_ctx.texture_id_for(image, options)
+    }
+
+    /// Draws the latest finished GUI frame to the screen.
+    ///
+    /// Problems such as a failed texture upload or a mesh referencing a
+    /// missing texture don't stop the frame: the rest is still drawn, missing
+    /// textures are replaced by a magenta and black checkerboard, and the
+    /// problems are returned.
+    ///
+    /// The platform output and viewport commands of `full_output` are left to
+    /// the caller. The frames the wrapper runs itself have their cursor icon,
+    /// screenshot requests and accessibility tree handled before drawing.
+    pub fn draw(
+        &mut self,
+        full_output: egui::FullOutput,
+        gfx: &mut Graphics2D,
+    ) -> Vec<WindowWrapperError> {
+        self.frame_stats.clear_draw();
+        self.draw_main(full_output, gfx)
+    }
+}
+
+impl<UserEventType> WindowWrapper<UserEventType, SoftwareImage> {
+    /// Creates a [`WindowWrapper`] drawing with a [`SoftwareRenderer`], to
+    /// run `handler` without a window, in a
+    /// [`testing::Driver`](crate::testing::Driver).
+    ///
+    /// Only the drawing callbacks of `handler` are run, with no
+    /// `WindowHelper`: [`WindowHandler::on_draw_before_ui`],
+    /// [`WindowHandler::on_draw`] and [`WindowHandler::on_draw_after_ui`]. The
+    /// drawing errors are returned by the frame instead of going to
+    /// [`WindowHandler::on_error`], and input reaches egui without going
+    /// through the input callbacks.
+    pub fn headless(handler: impl WindowHandler<UserEventType> + 'static) -> Self {
+        Self::with_handler(Box::new(handler))
+    }
+}
+
+impl<UserEventType, I: Clone + 'static> WindowWrapper<UserEventType, I> {
+    fn with_handler(handler: Box<dyn WindowHandler<UserEventType>>) -> Self {
+        let egui_ctx = Context::default();
+        #[cfg(feature = "image_loader")]
+        let image_loader = Arc::new(ImageLoader::default());
+        #[cfg(feature = "image_loader")]
+        egui_ctx.add_texture_loader(image_loader.clone());
         Self {
-            handler: Box::new(handler),
+            handler,
             raw_input: Default::default(),
-            egui_ctx: Default::default(),
-            id_and_textures: Default::default(),
-            to_free_textures: Default::default(),
+            egui_ctx,
+            painter: Default::default(),
             last_mouse_position: Vec2::new(0., 0.),
             current_modifiers: Default::default(),
+            software_cursor: false,
+            os_cursor_hidden: false,
+            frame_stats: Default::default(),
+            last_frame_start: None,
+            stats_overlay: None,
+            dev_tools: None,
+            contexts: Vec::new(),
+            #[cfg(feature = "image_loader")]
+            image_loader,
+            #[cfg(feature = "accesskit")]
+            tree_consumer: None,
+            #[cfg(feature = "accesskit")]
+            accesskit_actions: Default::default(),
+            #[cfg(feature = "record")]
+            input_recorder: None,
+            #[cfg(feature = "record")]
+            input_replay: None,
+            #[cfg(feature = "remote")]
+            remote_server: None,
+            #[cfg(feature = "mirror")]
+            mirror_server: None,
         }
     }
 
-    /// Draws the latest finished GUI frame to the screen.
-    pub fn draw(
+    /// Turns on egui's accessibility tree, and gives it to `consumer` every
+    /// frame.
+    ///
+    /// Action requests pushed to [`WindowWrapper::accesskit_actions`] are
+    /// given to egui on the next frame. They don't wake an idle window up by
+    /// themselves, see [`ActionQueue::set_waker`](accessibility::ActionQueue::set_waker).
+    #[cfg(feature = "accesskit")]
+    pub fn with_accesskit(mut self, consumer: impl accessibility::TreeConsumer + 'static) -> Self {
+        self.egui_ctx.enable_accesskit();
+        self.tree_consumer = Some(Box::new(consumer));
+        self
+    }
+
+    /// Returns the queue of action requests from assistive technologies.
+    #[cfg(feature = "accesskit")]
+    pub fn accesskit_actions(&self) -> accessibility::ActionQueue {
+        self.accesskit_actions.clone()
+    }
+
+    /// Records the egui input of every frame.
+    #[cfg(feature = "record")]
+    pub fn with_input_recorder(mut self, recorder: record::InputRecorder) -> Self {
+        self.input_recorder = Some(recorder);
+        self
+    }
+
+    /// Feeds egui the recorded input, frame by frame, in place of the live
+    /// events. Live input resumes once the recording is over.
+    ///
+    /// The handler's own callbacks still receive the live events.
+    #[cfg(feature = "record")]
+    pub fn with_input_replay(mut self, replay: record::InputReplay) -> Self {
+        self.input_replay = Some(replay);
+        self
+    }
+
+    /// Handles the commands sent to `server` at the start of every frame.
+    ///
+    /// The window is redrawn continuously while a client is connected, so that
+    /// its commands are handled even when the app is idle. A client connecting
+    /// to an idle window waits for its next redraw, for example on the next
+    /// input event.
+    #[cfg(feature = "remote")]
+    pub fn with_remote_server(mut self, server: remote::RemoteServer) -> Self {
+        self.remote_server = Some(server);
+        self
+    }
+
+    /// Sends every frame drawn to the viewers of `server`, and gives egui the
+    /// events they send back.
+    ///
+    /// The server keeps a copy of the textures of the main context, see
+    /// [`mirror::MirrorServer`].
+    #[cfg(feature = "mirror")]
+    pub fn with_mirror_server(mut self, server: mirror::MirrorServer) -> Self {
+        self.mirror_server = Some(server);
+        self
+    }
+
+    /// Shows the frame timings and draw statistics of the last frames over
+    /// the UI. The overlay starts hidden, and `key` toggles it.
+    ///
+    /// The numbers are measured whether the overlay is shown or not, see
+    /// [`FrameStats`].
+    pub fn with_stats_overlay(mut self, key: egui::Key) -> Self {
+        self.stats_overlay = Some(stats::StatsOverlay::new(key));
+        self
+    }
+
+    /// Adds a developer tools window, opened and closed with F12, showing
+    /// egui's settings, inspection, memory and texture panels, the textures
+    /// held for speedy2d and the [`DebugPaint`] switches.
+    ///
+    /// The key presses opening and closing the window reach neither egui nor
+    /// the handler.
+    pub fn with_dev_tools(self, enabled: bool) -> Self {
+        match enabled {
+            true => self.with_dev_tools_key(VirtualKeyCode::F12),
+            false => Self {
+                dev_tools: None,
+                ..self
+            },
+        }
+    }
+
+    /// Adds the developer tools window of [`WindowWrapper::with_dev_tools`],
+    /// opened and closed with `key`.
+    pub fn with_dev_tools_key(mut self, key: VirtualKeyCode) -> Self {
+        self.dev_tools = Some(dev_tools::DevTools::new(key));
+        self
+    }
+
+    /// Adds an egui context named `name`, with its own style, memory, zoom and
+    /// textures, replacing any context added under the same name.
+    ///
+    /// Its UI is built in [`WindowHandler::on_draw`], with the context found
+    /// by [`ContextNamedExt::named_context`]. The main context, the one given
+    /// to the handler, has priority 0. Contexts are drawn by increasing
+    /// priority, a named context over the main one when they have the same
+    /// priority, and the topmost context gets input first: it keeps the
+    /// pointer events while the pointer is over it, and the keyboard events
+    /// while it wants them.
+    ///
+    /// Textures created through [`ContextTexturesExt`] work in every context,
+    /// but the other features of [`WindowWrapper`], like the game views or the
+    /// debug drawings, only apply to the main context.
+    ///
+    /// The output of the contexts is merged in input order:
+    /// - the cursor icon is the first one that isn't the default, which is the
+    ///   one of the context under the pointer;
+    /// - a [`ViewportCommand::Screenshot`](egui::ViewportCommand::Screenshot)
+    ///   from any context takes a screenshot, sent to every context, and the
+    ///   files asked for with [`ContextScreenshotExt::save_screenshot`] in any
+    ///   context are written;
+    /// - the accessibility tree is only the one of the main context;
+    /// - copied text, URLs to open, other viewport commands and repaint
+    ///   delays are ignored, for the main context too.
+    pub fn with_context(mut self, name: impl Into<String>, priority: i32) -> Self {
+        let name = name.into();
+        self.contexts.retain(|c| c.name != name);
+        let ctx = Context::default();
+        if let Some(pixels_per_point) = self.raw_input.viewport().native_pixels_per_point {
+            ctx.set_pixels_per_point(pixels_per_point);
+        }
+        let index = self.contexts.partition_point(|c| c.priority <= priority);
+        self.contexts.insert(
+            index,
+            contexts::NamedContext {
+                name,
+                ctx,
+                priority,
+                painter: Default::default(),
+            },
+        );
+        contexts::publish(&self.egui_ctx, &self.contexts);
+        self
+    }
+
+    /// Returns the context added under `name` with
+    /// [`WindowWrapper::with_context`], if any.
+    pub fn context(&self, name: &str) -> Option<&Context> {
+        self.contexts
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| &c.ctx)
+    }
+
+    /// Returns the loader decoding the images shown with `egui::Image`.
+    #[cfg(feature = "image_loader")]
+    pub fn image_loader(&self) -> &ImageLoader {
+        &self.image_loader
+    }
+
+    /// Draws egui's cursor icons (resize arrows, text beam, grab hand...)
+    /// instead of the OS cursor when the pointer is over the egui UI.
+    ///
+    /// speedy2d can only show or hide the OS cursor, so without this the
+    /// cursor icons asked for by egui are lost. Defaults to `false`.
+    pub fn with_software_cursor(mut self, enabled: bool) -> Self {
+        self.software_cursor = enabled;
+        self
+    }
+
+    /// Sets which textures keep a copy of their pixels in CPU memory.
+    ///
+    /// Defaults to [`CpuCopyPolicy::FontAtlas`].
+    pub fn with_cpu_copy_policy(mut self, policy: CpuCopyPolicy) -> Self {
+        self.painter.set_cpu_copy_policy(policy);
+        self
+    }
+
+    /// Overrides the [`CpuCopyPolicy`] for a single texture.
+    ///
+    /// Turning the copy off drops it right away. Turning it on takes effect
+    /// the next time egui uploads the whole texture.
+    pub fn set_texture_cpu_copy(&mut self, texture_id: egui::TextureId, keep: bool) {
+        self.painter.set_texture_cpu_copy(texture_id, keep);
+    }
+
+    /// Returns statistics about the textures currently alive.
+    pub fn texture_stats(&self) -> TextureStats {
+        self.painter.texture_stats()
+    }
+
+    /// Creates a texture from straight (non-premultiplied) RGBA pixels, and
+    /// returns its id. The pixels are uploaded when the next frame is drawn.
+    ///
+    /// The same as [`ContextTexturesExt::load_texture_rgba`] on the main
+    /// context, which can be called from the handler.
+    pub fn load_texture_rgba(
+        &self,
+        name: impl Into<String>,
+        size: [usize; 2],
+        pixels: impl Into<Vec<u8>>,
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError> {
+        self.egui_ctx.load_texture_rgba(name, size, pixels, options)
+    }
+
+    /// Frees a texture created with [`WindowWrapper::texture_id_for`] or
+    /// [`WindowWrapper::load_texture_rgba`].
+    pub fn free_texture(&self, texture_id: egui::TextureId) {
+        self.egui_ctx.free_texture(texture_id);
+    }
+
+    /// Writes the next frame drawn to a PNG file at `path`, once the window is
+    /// fully drawn, the game included.
+    ///
+    /// From the handler, call [`ContextScreenshotExt::save_screenshot`] on the
+    /// egui context instead, which this delegates to.
+    pub fn request_screenshot(&self, path: impl Into<std::path::PathBuf>) {
+        self.egui_ctx.save_screenshot(path);
+    }
+
+    /// Draws a finished frame of the main context, see
+    /// [`WindowWrapper::draw`], adding to the draw statistics of the frame.
+    fn draw_main<R: WindowRenderer<Image = I>>(
         &mut self,
         full_output: egui::FullOutput,
-        gfx: &mut Graphics2D,
-    ) -> Result<(), BacktraceError<ErrorMessage>> {
-        // free old textures
-        self.free_textures();
+        gfx: &mut R,
+    ) -> Vec<WindowWrapperError> {
+        let mut errors = vec![];
+        let start = Instant::now();
+        textures::apply(&self.egui_ctx, &mut self.painter);
+        if let Err(error) = game_view::upload(&self.egui_ctx, &mut self.painter, gfx) {
+            errors.push(error);
+        }
+        #[cfg(feature = "image_loader")]
+        errors.extend(
+            self.image_loader
+                .upload(&self.egui_ctx, &mut self.painter, gfx),
+        );
+        let uploads = start.elapsed();
 
-        // get change
-        let clipped_primitives = self.egui_ctx.tessellate(full_output.shapes, full_output.pixels_per_point);
+        let ctx = &self.egui_ctx;
+        let start = Instant::now();
+        let primitives = {
+            profile_scope!("tessellate");
+            ctx.tessellate(full_output.shapes, full_output.pixels_per_point)
+        };
+        self.frame_stats.tessellation += start.elapsed();
+        self.frame_stats.count(&primitives);
 
-        // save textures to delete next frame
-        self.to_free_textures = full_output
-            .textures_delta
-            .free
-            .iter()
-            .filter_map(|t| match t {
-                egui::TextureId::Managed(id) => Some(*id),
-                egui::TextureId::User(_) => None,
-            })
-            .collect();
-
-        // set new textures
-        self.set_textures(full_output.textures_delta, gfx)?;
-
-        // draw
-        for egui::ClippedPrimitive {
-            clip_rect,
-            primitive,
-        } in clipped_primitives
+        #[cfg(feature = "mirror")]
+        if let Some(server) = &mut self.mirror_server {
+            server.send_frame(
+                ctx.screen_rect(),
+                full_output.pixels_per_point,
+                &full_output.textures_delta,
+                &primitives,
+            );
+        }
+
+        let debug_paint = ctx.debug_paint();
+        let debug_primitives = debug_paint.is_enabled().then(|| primitives.clone());
+
+        let start = Instant::now();
+        errors.extend(self.painter.paint_tessellated(
+            ctx,
+            full_output.textures_delta,
+            primitives,
+            gfx,
+        ));
+        if let Some(primitives) = debug_primitives {
+            debug_paint::paint(
+                debug_paint,
+                &primitives,
+                ctx.paint_transform(),
+                ctx.screen_rect(),
+                &self.painter,
+                gfx,
+            );
+        }
+        self.frame_stats.draw += uploads + start.elapsed();
+        self.frame_stats.add_textures(self.painter.texture_stats());
+        errors
+    }
+
+    /// Returns the statistics of the last frame drawn.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// Takes the input gathered since the last frame, or the next recorded
+    /// one when replaying.
+    fn take_raw_input(&mut self) -> RawInput {
+        #[cfg(feature = "accesskit")]
+        self.raw_input
+            .events
+            .extend(self.accesskit_actions.take_events());
+        #[cfg(feature = "remote")]
+        if let Some(server) = &mut self.remote_server {
+            server.handle_commands(&self.egui_ctx, &mut self.raw_input);
+        }
+        #[cfg(feature = "mirror")]
+        if let Some(server) = &self.mirror_server {
+            self.raw_input.events.extend(server.take_events());
+        }
+        let raw_input = self.raw_input.take();
+
+        #[cfg(feature = "record")]
+        let raw_input = match self.input_replay.as_mut().map(|r| r.next_input()) {
+            Some(Some(recorded)) => recorded,
+            Some(None) => {
+                log::info!("input replay finished, back to live input");
+                self.input_replay = None;
+                raw_input
+            }
+            None => raw_input,
+        };
+
+        #[cfg(feature = "record")]
+        if let Some(recorder) = &mut self.input_recorder {
+            if let Err(error) = recorder.record(&raw_input) {
+                log::error!("input recording stopped: {error}");
+                self.input_recorder = None;
+            }
+        }
+
+        if let Some(overlay) = &mut self.stats_overlay {
+            overlay.handle_events(&raw_input.events);
+        }
+
+        raw_input
+    }
+
+    /// Returns the last mouse position, mapped into the coordinate space of
+    /// the egui context through the inverse of its [`Transform`].
+    fn pointer_pos(&self) -> egui::Pos2 {
+        let pos = pos2_from_speedy2d(self.last_mouse_position);
+        match self.egui_ctx.paint_transform().inverse() {
+            Some(inverse) => inverse.apply(pos),
+            None => pos,
+        }
+    }
+
+    /// Runs a frame of the handler and of every context, and draws it on
+    /// `renderer`.
+    ///
+    /// `helper` is given to the drawing callbacks of the handler, it is
+    /// `None` without a window.
+    pub(crate) fn frame<R: WindowRenderer<Image = I>>(
+        &mut self,
+        renderer: &mut R,
+        helper: Option<&mut WindowHelper<UserEventType>>,
+    ) -> FrameEnd {
+        // the handler is lent to the frame, which borrows the wrapper too
+        let mut handler = std::mem::replace(&mut self.handler, Box::new(NoHandler));
+        let end = self.frame_with(handler.as_mut(), renderer, helper);
+        self.handler = handler;
+        end
+    }
+
+    /// Runs a frame of `handler` in place of the one of the wrapper, for
+    /// handlers that don't live as long as the wrapper.
+    pub(crate) fn frame_with<R: WindowRenderer<Image = I>>(
+        &mut self,
+        handler: &mut dyn WindowHandler<UserEventType>,
+        renderer: &mut R,
+        mut helper: Option<&mut WindowHelper<UserEventType>>,
+    ) -> FrameEnd {
+        let frame_start = Instant::now();
+        if let Some(last_frame_start) = self.last_frame_start.replace(frame_start) {
+            self.frame_stats.frame_time = frame_start - last_frame_start;
+        }
+        handler.on_draw_before_ui(helper.as_deref_mut(), renderer, &self.egui_ctx);
+        if let Some(region) = game_view::capture_region(&self.egui_ctx) {
+            game_view::set_captured(&self.egui_ctx, &renderer.capture(), region);
+        }
+        // extract events and begin frame
+        let raw_input = self.take_raw_input();
+        let (raw_input, context_inputs) =
+            contexts::route(&self.egui_ctx, &self.contexts, raw_input);
+        let ctx = &self.egui_ctx;
         {
-            gfx.set_clip(Some(rect_from_egui(clip_rect)));
-            if let epaint::Primitive::Mesh(epaint::Mesh {
-                indices,
-                vertices,
-                texture_id,
-            }) = primitive
-            {
-                let texture_id = match texture_id {
-                    egui::TextureId::Managed(id) => id,
-                    egui::TextureId::User(_) => continue,
-                };
-
-                let handle = self.id_and_textures.get(&texture_id).unwrap().0.clone();
-                for indices in indices.chunks_exact(3) {
-                    let mut v = indices
+            profile_scope!("begin_frame");
+            ctx.begin_frame(raw_input);
+            for (context, raw_input) in self.contexts.iter().zip(context_inputs) {
+                context.ctx.begin_frame(raw_input);
+            }
+        }
+        contexts::publish(ctx, &self.contexts);
+        let start = Instant::now();
+        {
+            profile_scope!("on_draw");
+            handler.on_draw(helper.as_deref_mut(), renderer, ctx);
+        }
+        self.frame_stats.on_draw = start.elapsed();
+        if let Some(overlay) = &self.stats_overlay {
+            overlay.show(ctx);
+        }
+        if let Some(dev_tools) = &mut self.dev_tools {
+            dev_tools.show(ctx, &self.painter);
+        }
+        let start = Instant::now();
+        let (full_output, context_outputs) = {
+            profile_scope!("end_frame");
+            let context_outputs: Vec<_> = self.contexts.iter().map(|c| c.ctx.end_frame()).collect();
+            (ctx.end_frame(), context_outputs)
+        };
+        self.frame_stats.end_frame = start.elapsed();
+        #[cfg(feature = "accesskit")]
+        let mut full_output = full_output;
+        #[cfg(feature = "accesskit")]
+        if let Some(update) = full_output.platform_output.accesskit_update.take() {
+            if let Some(consumer) = &mut self.tree_consumer {
+                consumer.update_tree(update);
+            }
+        }
+        let outputs = contexts::input_order(&self.contexts, &full_output, &context_outputs);
+        let screenshot_requested = outputs.iter().any(|output| {
+            output
+                .viewport_output
+                .get(&egui::ViewportId::ROOT)
+                .is_some_and(|output| {
+                    output
+                        .commands
                         .iter()
-                        .map(|i| vertices[*i as usize])
-                        .collect::<Vec<_>>();
-                    let mut p = v.iter().map(|v| vec2_from_egui(v.pos)).collect::<Vec<_>>();
-
-                    // dots must be in clockwise order
-                    let cross_product = (p[1].x - p[0].x) * (p[2].y - p[0].y)
-                        - (p[1].y - p[0].y) * (p[2].x - p[0].x);
-                    if cross_product.is_sign_positive() {
-                        v.swap(1, 2);
-                        p.swap(1, 2);
+                        .any(|command| matches!(command, egui::ViewportCommand::Screenshot))
+                })
+        });
+        let cursor_icon = outputs
+            .iter()
+            .map(|output| output.platform_output.cursor_icon)
+            .find(|icon| *icon != egui::CursorIcon::Default)
+            .unwrap_or_default();
+        let mut full_output = Some(full_output);
+        let mut context_outputs: Vec<_> = context_outputs.into_iter().map(Some).collect();
+        let mut end = FrameEnd {
+            errors: vec![],
+            redraw: false,
+            cursor_visible: None,
+        };
+        self.frame_stats.clear_draw();
+        for slot in contexts::render_order(&self.contexts) {
+            match slot {
+                None => {
+                    if let Some(full_output) = full_output.take() {
+                        end.errors.extend(self.draw_main(full_output, renderer));
+                    }
+                }
+                Some(i) => {
+                    let context = &mut self.contexts[i];
+                    if let Some(output) = context_outputs[i].take() {
+                        let start = Instant::now();
+                        let primitives = context
+                            .ctx
+                            .tessellate(output.shapes, output.pixels_per_point);
+                        self.frame_stats.tessellation += start.elapsed();
+                        self.frame_stats.count(&primitives);
+                        let debug_paint = context.ctx.debug_paint();
+                        let debug_primitives = debug_paint.is_enabled().then(|| primitives.clone());
+                        let start = Instant::now();
+                        textures::apply(&context.ctx, &mut context.painter);
+                        end.errors.extend(context.painter.paint_tessellated(
+                            &context.ctx,
+                            output.textures_delta,
+                            primitives,
+                            renderer,
+                        ));
+                        if let Some(primitives) = debug_primitives {
+                            debug_paint::paint(
+                                debug_paint,
+                                &primitives,
+                                context.ctx.paint_transform(),
+                                context.ctx.screen_rect(),
+                                &context.painter,
+                                renderer,
+                            );
+                        }
+                        self.frame_stats.draw += start.elapsed();
+                        self.frame_stats
+                            .add_textures(context.painter.texture_stats());
                     }
+                }
+            }
+        }
+        self.frame_stats.publish(&self.egui_ctx);
+        if let Some(overlay) = &mut self.stats_overlay {
+            overlay.push(self.frame_stats);
+        }
+        handler.on_draw_after_ui(helper, renderer, &self.egui_ctx);
 
-                    let colors = v
-                        .iter()
-                        .map(|v| color_from_egui(v.color))
-                        .collect::<Vec<_>>();
-                    let uvs = v.iter().map(|v| vec2_from_egui(v.uv)).collect::<Vec<_>>();
-
-                    gfx.draw_triangle_image_tinted_three_color(
-                        p.try_into().unwrap(),
-                        colors.try_into().unwrap(),
-                        uvs.try_into().unwrap(),
-                        &handle,
-                    );
+        let screenshot_paths = std::iter::once(&self.egui_ctx)
+            .chain(self.contexts.iter().map(|c| &c.ctx))
+            .flat_map(screenshot::take_paths)
+            .collect::<Vec<_>>();
+        if screenshot_requested || !screenshot_paths.is_empty() {
+            let image = renderer.capture();
+            for path in screenshot_paths {
+                let pixels = image.pixels.iter().flat_map(|c| c.to_array());
+                let pixels = pixels.collect::<Vec<_>>();
+                let size = [image.size[0] as u32, image.size[1] as u32];
+                if let Err(source) = png_io::write(&path, size, &pixels) {
+                    end.errors
+                        .push(WindowWrapperError::Screenshot { path, source });
                 }
-            } else {
-                todo!();
             }
+            if screenshot_requested {
+                self.raw_input.events.push(egui::Event::Screenshot {
+                    viewport_id: egui::ViewportId::ROOT,
+                    image: std::sync::Arc::new(image),
+                });
+                end.redraw = true;
+            }
+        }
+
+        if self.software_cursor {
+            let over_ui = std::iter::once(&self.egui_ctx)
+                .chain(self.contexts.iter().map(|c| &c.ctx))
+                .any(|ctx| ctx.is_pointer_over_area() || ctx.is_using_pointer());
+            if over_ui != self.os_cursor_hidden {
+                end.cursor_visible = Some(!over_ui);
+                self.os_cursor_hidden = over_ui;
+            }
+            if over_ui {
+                let pos = pos2_from_speedy2d(self.last_mouse_position);
+                end.errors.extend(self.painter.paint_cursor(
+                    &self.egui_ctx,
+                    cursor_icon,
+                    pos,
+                    renderer,
+                ));
+            }
+        }
+
+        #[cfg(feature = "remote")]
+        if let Some(server) = &self.remote_server {
+            end.redraw |= server.client_count() > 0;
+        }
+        end
+    }
+
+    /// Feeds egui a new size of the window.
+    pub(crate) fn input_resize(&mut self, size_pixels: UVec2) {
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_max(
+            Default::default(),
+            pos_from_uvec2(size_pixels),
+        ));
+    }
+
+    /// Feeds egui a new scale factor of the window.
+    pub(crate) fn input_scale_factor(&mut self, scale_factor: f64) {
+        self.egui_ctx.set_pixels_per_point(scale_factor as f32);
+        for context in &self.contexts {
+            context.ctx.set_pixels_per_point(scale_factor as f32);
         }
+    }
 
-        // todo handle platform output
+    /// Feeds egui a new mouse position, in window pixels.
+    pub(crate) fn input_mouse_move(&mut self, position: Vec2) {
+        self.last_mouse_position = position;
+        self.raw_input
+            .events
+            .push(egui::Event::PointerMoved(self.pointer_pos()));
+    }
 
-        Ok(())
+    /// Feeds egui a mouse button press or release.
+    pub(crate) fn input_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        let button = match button {
+            MouseButton::Left => egui::PointerButton::Primary,
+            MouseButton::Right => egui::PointerButton::Secondary,
+            MouseButton::Middle => egui::PointerButton::Middle,
+            MouseButton::Other(_) => return,
+        };
+        self.raw_input.events.push(egui::Event::PointerButton {
+            pos: self.pointer_pos(),
+            button,
+            pressed,
+            modifiers: modifiers_from_speedy2d(&self.current_modifiers),
+        });
     }
 
-    fn set_textures(
+    /// Feeds egui a key press or release, and returns whether the handler
+    /// should get it too. The key of the developer tools toggles them and
+    /// reaches neither.
+    pub(crate) fn input_key(
         &mut self,
-        textures_delta: egui::TexturesDelta,
-        gfx: &mut Graphics2D,
-    ) -> Result<(), BacktraceError<ErrorMessage>> {
-        for (texture_id, image_delta) in textures_delta.set {
-            let id = match texture_id {
-                egui::TextureId::Managed(texture_id) => texture_id,
-                egui::TextureId::User(_) => continue,
-            };
-
-            let image = RgbaImage::from(image_delta.image);
-            if let Some(_pos) = image_delta.pos {
-                todo!();
-            } else {
-                let handle = gfx.create_image_from_raw_pixels(
-                    ImageDataType::RGBA,
-                    match image_delta.options {
-                        egui::TextureOptions::NEAREST => ImageSmoothingMode::NearestNeighbor,
-                        egui::TextureOptions::LINEAR => ImageSmoothingMode::Linear,
-                        _ => ImageSmoothingMode::Linear,
-                    },
-                    UVec2::new(image.size.0 as u32, image.size.1 as u32),
-                    &image.pixels,
-                )?;
-                self.id_and_textures.insert(id, (handle, image));
+        virtual_key_code: Option<VirtualKeyCode>,
+        pressed: bool,
+    ) -> bool {
+        if let Some(dev_tools) = &mut self.dev_tools {
+            if virtual_key_code == Some(dev_tools.key) {
+                if pressed {
+                    dev_tools.toggle();
+                }
+                return false;
             }
         }
-        Ok(())
+        if let Some(key) = key_from_speedy2d(virtual_key_code) {
+            self.raw_input.events.push(egui::Event::Key {
+                key,
+                pressed,
+                repeat: false,
+                modifiers: modifiers_from_speedy2d(&self.current_modifiers),
+                physical_key: None,
+            });
+        }
+        true
     }
 
-    fn free_textures(&mut self) {
-        for id in self.to_free_textures.drain(..) {
-            self.id_and_textures.remove(&id);
-        }
+    /// Feeds egui a typed character.
+    pub(crate) fn input_char(&mut self, unicode_codepoint: char) {
+        self.raw_input
+            .events
+            .push(egui::Event::Text(unicode_codepoint.to_string()));
+    }
+
+    /// Feeds egui the new state of the modifier keys.
+    pub(crate) fn input_modifiers(&mut self, state: &ModifiersState) {
+        self.current_modifiers = state.clone();
+        self.raw_input.modifiers = modifiers_from_speedy2d(state);
     }
 }
 
+/// Stands in for the handler of a [`WindowWrapper`] while it is lent to a
+/// frame.
+pub(crate) struct NoHandler;
+
+impl<UserEventType> WindowHandler<UserEventType> for NoHandler {}
+
+/// What a frame asks of the window, once drawn.
+pub(crate) struct FrameEnd {
+    pub(crate) errors: Vec<WindowWrapperError>,
+    /// Whether another frame should be drawn right away.
+    pub(crate) redraw: bool,
+    /// Whether to show or hide the OS cursor, when it changes.
+    pub(crate) cursor_visible: Option<bool>,
+}
+
 /// A trait analogous to [`speedy2d::window::WindowHandler`], but with the
 /// addition of a [`egui_ctx`](egui::Context) argument.
 ///
@@ -284,18 +955,73 @@ pub trait WindowHandler<UserEventType = ()> {
     ) {
     }
 
+    /// Invoked when the contents of the window needs to be redrawn, before the
+    /// egui frame begins.
+    ///
+    /// Anything drawn here ends up under the egui UI. Use it to keep the game's
+    /// drawing separate from building the egui frame in
+    /// [WindowHandler::on_draw].
+    ///
+    /// Like the other drawing callbacks, it draws through a [`Canvas`] and
+    /// gets no `helper` when run without a window, in a
+    /// [`testing::Driver`](crate::testing::Driver).
+    #[allow(unused_variables)]
+    #[inline]
+    fn on_draw_before_ui(
+        &mut self,
+        helper: Option<&mut WindowHelper<UserEventType>>,
+        canvas: &mut dyn Canvas,
+        egui_ctx: &egui::Context,
+    ) {
+    }
+
     /// Invoked when the contents of the window needs to be redrawn.
     ///
+    /// This is called during the egui frame, so the UI can be built here. Anything
+    /// drawn on `canvas` ends up under the egui UI.
+    ///
+    /// `helper` is `None` without a window, in a
+    /// [`testing::Driver`](crate::testing::Driver), which runs this callback
+    /// too so that the UI can be tested end to end.
+    ///
     /// It is possible to request a redraw from any callback using
     /// [WindowHelper::request_redraw].
     #[allow(unused_variables)]
     #[inline]
     fn on_draw(
+        &mut self,
+        helper: Option<&mut WindowHelper<UserEventType>>,
+        canvas: &mut dyn Canvas,
+        egui_ctx: &egui::Context,
+    ) {
+    }
+
+    /// Invoked once the egui meshes have been painted.
+    ///
+    /// Anything drawn here ends up over the egui UI, for example a drag and
+    /// drop preview, a screen fade or a software cursor.
+    #[allow(unused_variables)]
+    #[inline]
+    fn on_draw_after_ui(
+        &mut self,
+        helper: Option<&mut WindowHelper<UserEventType>>,
+        canvas: &mut dyn Canvas,
+        egui_ctx: &egui::Context,
+    ) {
+    }
+
+    /// Invoked when drawing the egui UI runs into a problem.
+    ///
+    /// Drawing carries on when this happens. By default the error is logged.
+    #[allow(unused_variables)]
+    #[inline]
+    fn on_error(
         &mut self,
         helper: &mut WindowHelper<UserEventType>,
-        graphics: &mut Graphics2D,
+        error: WindowWrapperError,
         egui_ctx: &egui::Context,
     ) {
+        log::error!("{error}");
     }
 
     /// Invoked when the mouse changes position.
@@ -431,10 +1157,7 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
     #[allow(unused_variables)]
     #[inline]
     fn on_resize(&mut self, helper: &mut WindowHelper<UserEventType>, size_pixels: UVec2) {
-        self.raw_input.screen_rect = Some(egui::Rect::from_min_max(
-            Default::default(),
-            pos_from_uvec2(size_pixels),
-        ));
+        self.input_resize(size_pixels);
         self.handler.on_resize(helper, size_pixels, &self.egui_ctx);
     }
 
@@ -475,7 +1198,7 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         helper: &mut WindowHelper<UserEventType>,
         scale_factor: f64,
     ) {
-        self.egui_ctx.set_pixels_per_point(scale_factor as f32);
+        self.input_scale_factor(scale_factor);
         self.handler
             .on_scale_factor_changed(helper, scale_factor, &self.egui_ctx);
     }
@@ -487,14 +1210,17 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
     #[allow(unused_variables)]
     #[inline]
     fn on_draw(&mut self, helper: &mut WindowHelper<UserEventType>, graphics: &mut Graphics2D) {
-        let ctx = &self.egui_ctx;
-        // extract events and begin frame
-        let raw_input = self.raw_input.take();
-        ctx.begin_frame(raw_input);
-        self.handler.on_draw(helper, graphics, ctx);
-        let full_output = ctx.end_frame();
-        // speedy2d doesn't authorize errors. So... panic.
-        self.draw(full_output, graphics).unwrap();
+        let end = self.frame(graphics, Some(&mut *helper));
+        if let Some(visible) = end.cursor_visible {
+            helper.set_cursor_visible(visible);
+        }
+        if end.redraw {
+            helper.request_redraw();
+        }
+        // speedy2d doesn't authorize errors, let the handler deal with them
+        for error in end.errors {
+            self.handler.on_error(helper, error, &self.egui_ctx);
+        }
     }
 
     /// Invoked when the mouse changes position.
@@ -508,10 +1234,7 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
     #[allow(unused_variables)]
     #[inline]
     fn on_mouse_move(&mut self, helper: &mut WindowHelper<UserEventType>, position: Vec2) {
-        self.last_mouse_position = position;
-        self.raw_input
-            .events
-            .push(egui::Event::PointerMoved(pos2_from_speedy2d(position)));
+        self.input_mouse_move(position);
         self.handler.on_mouse_move(helper, position, &self.egui_ctx);
     }
 
@@ -523,19 +1246,7 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         helper: &mut WindowHelper<UserEventType>,
         button: MouseButton,
     ) {
-        if let Some(button) = match button {
-            MouseButton::Left => Some(egui::PointerButton::Primary),
-            MouseButton::Right => Some(egui::PointerButton::Secondary),
-            MouseButton::Middle => Some(egui::PointerButton::Middle),
-            MouseButton::Other(btn) => None,
-        } {
-            self.raw_input.events.push(egui::Event::PointerButton {
-                pos: pos2_from_speedy2d(self.last_mouse_position),
-                button,
-                pressed: true,
-                modifiers: modifiers_from_speedy2d(&self.current_modifiers),
-            });
-        }
+        self.input_mouse_button(button, true);
         self.handler
             .on_mouse_button_down(helper, button, &self.egui_ctx);
     }
@@ -548,19 +1259,7 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         helper: &mut WindowHelper<UserEventType>,
         button: MouseButton,
     ) {
-        if let Some(button) = match button {
-            MouseButton::Left => Some(egui::PointerButton::Primary),
-            MouseButton::Right => Some(egui::PointerButton::Secondary),
-            MouseButton::Middle => Some(egui::PointerButton::Middle),
-            MouseButton::Other(btn) => None,
-        } {
-            self.raw_input.events.push(egui::Event::PointerButton {
-                pos: pos2_from_speedy2d(self.last_mouse_position),
-                button,
-                pressed: false,
-                modifiers: modifiers_from_speedy2d(&self.current_modifiers),
-            });
-        }
+        self.input_mouse_button(button, false);
         self.handler
             .on_mouse_button_up(helper, button, &self.egui_ctx);
     }
@@ -589,17 +1288,10 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         virtual_key_code: Option<VirtualKeyCode>,
         scancode: KeyScancode,
     ) {
-        if let Some(key) = key_from_speedy2d(virtual_key_code) {
-            self.raw_input.events.push(egui::Event::Key {
-                key,
-                pressed: true,
-                repeat: false,
-                modifiers: modifiers_from_speedy2d(&self.current_modifiers),
-                physical_key: None,
-            });
+        if self.input_key(virtual_key_code, true) {
+            self.handler
+                .on_key_down(helper, virtual_key_code, scancode, &self.egui_ctx);
         }
-        self.handler
-            .on_key_down(helper, virtual_key_code, scancode, &self.egui_ctx);
     }
 
     /// Invoked when a keyboard key is released.
@@ -611,17 +1303,10 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         virtual_key_code: Option<VirtualKeyCode>,
         scancode: KeyScancode,
     ) {
-        if let Some(key) = key_from_speedy2d(virtual_key_code) {
-            self.raw_input.events.push(egui::Event::Key {
-                key,
-                pressed: false,
-                repeat: false,
-                modifiers: modifiers_from_speedy2d(&self.current_modifiers),
-                physical_key: None,
-            });
+        if self.input_key(virtual_key_code, false) {
+            self.handler
+                .on_key_up(helper, virtual_key_code, scancode, &self.egui_ctx);
         }
-        self.handler
-            .on_key_up(helper, virtual_key_code, scancode, &self.egui_ctx);
     }
 
     /// Invoked when a character is typed on the keyboard.
@@ -635,9 +1320,7 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         helper: &mut WindowHelper<UserEventType>,
         unicode_codepoint: char,
     ) {
-        self.raw_input
-            .events
-            .push(egui::Event::Text(unicode_codepoint.to_string()));
+        self.input_char(unicode_codepoint);
         self.handler
             .on_keyboard_char(helper, unicode_codepoint, &self.egui_ctx);
     }
@@ -650,23 +1333,86 @@ impl<UserEventType> speedy2d::window::WindowHandler<UserEventType>
         helper: &mut WindowHelper<UserEventType>,
         state: ModifiersState,
     ) {
-        self.current_modifiers = state.clone();
-        self.raw_input.modifiers = modifiers_from_speedy2d(&state);
+        self.input_modifiers(&state);
         self.handler
             .on_keyboard_modifiers_changed(helper, state, &self.egui_ctx);
     }
 }
 
+/// Picks the speedy2d smoothing mode closest to egui's texture options.
+///
+/// speedy2d uses a single filter for both magnification and minification. When
+/// egui asks for different ones, the magnification filter wins, since it is
+/// the most visible one (e.g. pixel art zoomed in with nearest filtering).
+fn smoothing_mode_from_egui(options: egui::TextureOptions) -> ImageSmoothingMode {
+    match options.magnification {
+        egui::TextureFilter::Nearest => ImageSmoothingMode::NearestNeighbor,
+        egui::TextureFilter::Linear => ImageSmoothingMode::Linear,
+    }
+}
+
 fn rect_from_egui(rect: egui::Rect) -> speedy2d::shape::Rectangle<i32> {
-    speedy2d::shape::Rectangle::new(ivec2_from_egui(rect.min), ivec2_from_egui(rect.max))
+    // keep far enough from the i32 limits for speedy2d to compute sizes
+    const LIMIT: f32 = (1 << 24) as f32;
+    let min = rect
+        .min
+        .clamp(egui::pos2(-LIMIT, -LIMIT), egui::pos2(LIMIT, LIMIT));
+    let max = rect.max.clamp(min, egui::pos2(LIMIT, LIMIT));
+    speedy2d::shape::Rectangle::new(ivec2_from_egui(min), ivec2_from_egui(max))
 }
 
 fn pos_from_uvec2(pos: UVec2) -> egui::Pos2 {
     egui::Pos2::new(pos.x as f32, pos.y as f32)
 }
 
-fn color_from_egui(color: epaint::Color32) -> Color {
-    Color::from_int_rgba(color.r(), color.g(), color.b(), color.a())
+/// Converts an egui color to a speedy2d color.
+///
+/// egui colors are premultiplied sRGBA, and egui expects them to be blended
+/// in gamma space as `src + (1 - src_alpha) * dst`. speedy2d blends straight
+/// (non-premultiplied) alpha as `src * src_alpha + (1 - src_alpha) * dst`, so
+/// the color channels are divided by alpha, in gamma space, to get the same
+/// result on screen.
+///
+/// Additive colors (zero alpha but non-zero color) can't be expressed with
+/// straight alpha and come out fully transparent.
+pub(crate) fn color_from_egui(color: epaint::Color32) -> Color {
+    let [r, g, b, a] = unmultiply(color);
+    Color::from_int_rgba(r, g, b, a)
+}
+
+/// Converts the vertex colors of a triangle with [`color_from_egui`].
+///
+/// speedy2d interpolates straight alpha between the vertices, so a fully
+/// transparent vertex, such as the feathering egui adds around shapes for
+/// anti-aliasing, would pull the color of the whole triangle towards black.
+/// It takes the color of the most opaque vertex instead, which gives the same
+/// result as egui's premultiplied interpolation.
+fn triangle_colors(colors: [epaint::Color32; 3]) -> [Color; 3] {
+    let opaque = colors.into_iter().max_by_key(|c| c.a()).unwrap_or_default();
+    let [r, g, b, _] = unmultiply(opaque);
+    colors.map(|color| match color.a() {
+        0 => Color::from_int_rgba(r, g, b, 0),
+        _ => color_from_egui(color),
+    })
+}
+
+/// Divides the color channels of a premultiplied sRGBA color by its alpha,
+/// in gamma space.
+fn unmultiply(color: epaint::Color32) -> [u8; 4] {
+    let a = color.a();
+    match a {
+        0 => [0, 0, 0, 0],
+        255 => color.to_array(),
+        _ => {
+            let unmultiply = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+            [
+                unmultiply(color.r()),
+                unmultiply(color.g()),
+                unmultiply(color.b()),
+                a,
+            ]
+        }
+    }
 }
 
 fn vec2_from_egui(pos: egui::Pos2) -> speedy2d::dimen::Vec2 {
@@ -770,40 +1516,154 @@ fn key_from_speedy2d(virtual_key_code: Option<VirtualKeyCode>) -> Option<egui::K
     }
 }
 
-struct RgbaImage {
-    size: (usize, usize),
-    pixels: Vec<u8>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl RgbaImage {
-    fn from(image: egui::ImageData) -> Self {
-        Self {
-            size: {
-                let size = image.size();
-                (size[0], size[1])
-            },
-            pixels: match image {
-                egui::ImageData::Font(font_image) => {
-                    let mut pixels = vec![];
-                    for color in font_image.srgba_pixels(None) {
-                        pixels.push(color.r());
-                        pixels.push(color.g());
-                        pixels.push(color.b());
-                        pixels.push(color.a());
-                    }
-                    pixels
+    /// Paints a frame of `ui` over a white background.
+    fn render(ui: impl FnMut(&Context)) -> SoftwareRenderer {
+        let ctx = Context::default();
+        let raw_input = RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                Default::default(),
+                egui::vec2(64., 64.),
+            )),
+            ..Default::default()
+        };
+        let full_output = ctx.run(raw_input, ui);
+        let mut renderer = SoftwareRenderer::new((64, 64));
+        renderer.clear(Color::WHITE);
+        let errors = Painter::default().paint(&ctx, full_output, &mut renderer);
+        assert!(errors.is_empty(), "{errors:?}");
+        renderer
+    }
+
+    /// Checks that red drawn over white blends like egui's premultiplied
+    /// blending, which keeps the red channel at 255 whatever the coverage,
+    /// and that some pixels are partially covered.
+    fn assert_red_over_white(renderer: &SoftwareRenderer) {
+        let mut partially_covered = 0;
+        for y in 0..64 {
+            for x in 0..64 {
+                let [r, g, b, _] = renderer.pixel(x, y);
+                assert!(r >= 254, "pixel ({x}, {y}) is darkened: {:?}", [r, g, b]);
+                assert!(g.abs_diff(b) <= 1, "pixel ({x}, {y}): {:?}", [r, g, b]);
+                if (16..240).contains(&g) {
+                    partially_covered += 1;
                 }
-                egui::ImageData::Color(color_image) => {
-                    let mut pixels = vec![];
-                    for color in &color_image.pixels {
-                        pixels.push(color.r());
-                        pixels.push(color.g());
-                        pixels.push(color.b());
-                        pixels.push(color.a());
-                    }
-                    pixels
+            }
+        }
+        assert!(partially_covered > 0);
+    }
+
+    #[test]
+    fn anti_aliased_edges_fade_without_darkening() {
+        let renderer = render(|ctx| {
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            painter.circle_filled(egui::pos2(31.3, 30.7), 20.2, egui::Color32::RED);
+        });
+        assert_red_over_white(&renderer);
+    }
+
+    #[test]
+    fn glyph_edges_fade_without_darkening() {
+        let renderer = render(|ctx| {
+            // zoomed in, so that the font atlas is filtered between texels
+            ctx.set_paint_transform(Transform::scale(2.7));
+            let painter = ctx.layer_painter(egui::LayerId::background());
+            painter.text(
+                egui::pos2(1.3, 2.6),
+                egui::Align2::LEFT_TOP,
+                "eg",
+                egui::FontId::proportional(14.),
+                egui::Color32::RED,
+            );
+        });
+        assert_red_over_white(&renderer);
+    }
+
+    /// A gray panel over the top left quarter of the window.
+    struct Panel;
+
+    impl WindowHandler for Panel {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &Context,
+        ) {
+            egui::Area::new("panel".into())
+                .fixed_pos(egui::Pos2::ZERO)
+                .show(egui_ctx, |ui| {
+                    let size = egui::vec2(50., 50.);
+                    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                    ui.painter()
+                        .rect_filled(rect, 0., egui::Color32::from_gray(100));
+                });
+        }
+    }
+
+    /// Moves the pointer to `pos` and runs a few frames, returning the cursor
+    /// visibility changes they asked for and the last rendering.
+    fn move_pointer(
+        window: &mut WindowWrapper<(), SoftwareImage>,
+        pos: Vec2,
+    ) -> (Vec<bool>, SoftwareRenderer) {
+        window.input_mouse_move(pos);
+        let mut changes = vec![];
+        let mut renderer = SoftwareRenderer::new((100, 100));
+        for _ in 0..3 {
+            renderer = SoftwareRenderer::new((100, 100));
+            let end = window.frame(&mut renderer, None);
+            assert!(end.errors.is_empty(), "{:?}", end.errors);
+            changes.extend(end.cursor_visible);
+        }
+        (changes, renderer)
+    }
+
+    #[test]
+    fn software_cursor_is_drawn_over_the_ui_only() {
+        let mut window = WindowWrapper::headless(Panel).with_software_cursor(true);
+        window.input_resize(UVec2::new(100, 100));
+
+        let (changes, renderer) = move_pointer(&mut window, Vec2::new(20., 20.));
+        assert_eq!(changes, [false]);
+        // inside the arrow, and on the panel away from it
+        assert_eq!(renderer.pixel(22, 28), [255; 4]);
+        assert_eq!(renderer.pixel(40, 20), [100, 100, 100, 255]);
+
+        let (changes, renderer) = move_pointer(&mut window, Vec2::new(80., 80.));
+        assert_eq!(changes, [true]);
+        assert_eq!(renderer.pixel(82, 88), [0; 4]);
+        assert_eq!(renderer.pixel(22, 28), [100, 100, 100, 255]);
+    }
+
+    #[test]
+    fn errors_can_be_sent_between_threads() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<WindowWrapperError>();
+    }
+
+    #[test]
+    fn color_from_egui_blends_like_egui() {
+        // what egui expects on screen
+        fn egui_blend(src: u8, src_alpha: u8, dst: u8) -> f32 {
+            src as f32 + (255. - src_alpha as f32) / 255. * dst as f32
+        }
+        // what speedy2d puts on screen
+        fn speedy2d_blend(src: f32, src_alpha: f32, dst: u8) -> f32 {
+            255. * (src * src_alpha + (1. - src_alpha) * dst as f32 / 255.)
+        }
+
+        for a in 1..=255u8 {
+            for c in (0..=a).step_by(7).chain([a]) {
+                let color = color_from_egui(egui::Color32::from_rgba_premultiplied(c, c, c, a));
+                for dst in [0, 64, 128, 255] {
+                    let expected = egui_blend(c, a, dst);
+                    let actual = speedy2d_blend(color.r(), color.a(), dst);
+                    assert!((expected - actual).abs() <= 1., "{c} {a} {dst}");
                 }
-            },
+            }
         }
     }
 }
@@ -0,0 +1,1021 @@
+//! Streaming of egui frames to viewers running in other processes, for
+//! example to look at the debug UI of a simulation server from a workstation.
+//!
+//! A [`MirrorServer`] sends the tessellated primitives and texture changes of
+//! every frame to the viewers connected to it, and gathers the input events
+//! they send back. Viewers connect with a [`MirrorClient`], or with the
+//! `egui_speedy2d_viewer` binary built with the `mirror` feature:
+//!
+//! ```text
+//! $ egui_speedy2d_viewer 192.168.1.20:7071
+//! ```
+//!
+//! Frames are sent in a compact binary format, input events as JSON lines.
+//! Only textures managed by egui are sent: user textures, such as a
+//! [`GameView`](crate::GameView) or a [`StreamingTexture`](crate::StreamingTexture),
+//! show as a checkerboard on the viewers.
+//!
+//! Both ends can run in the same process, over the loopback interface:
+//!
+//! ```
+//! use egui_speedy2d::mirror::{MirrorClient, MirrorServer};
+//! use egui_speedy2d::{Painter, SoftwareRenderer, Transform};
+//!
+//! let mut server = MirrorServer::bind("127.0.0.1:0").unwrap();
+//! let addr = server.local_addr();
+//! let viewer = std::thread::spawn(move || {
+//!     let mut client = MirrorClient::connect(addr).unwrap();
+//!     let frame = client.recv_frame().unwrap();
+//!     let mut renderer = SoftwareRenderer::new((200, 100));
+//!     let mut painter = Painter::default();
+//!     let errors = painter.paint_primitives(
+//!         frame.textures_delta,
+//!         frame.primitives,
+//!         Transform::IDENTITY,
+//!         &mut renderer,
+//!     );
+//!     assert!(errors.is_empty());
+//!     client.send_events(&[egui::Event::Text("hi".to_owned())]).unwrap();
+//!     renderer.pixel(190, 90)
+//! });
+//!
+//! let ctx = egui::Context::default();
+//! let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+//! let mut events = vec![];
+//! while events.is_empty() {
+//!     assert!(std::time::Instant::now() < deadline, "the viewer sent no events");
+//!     // frames are sent until the viewer is connected and has its first one
+//!     if server.viewer_count() == 0 {
+//!         let raw_input = egui::RawInput {
+//!             screen_rect: Some(egui::Rect::from_min_size(Default::default(), egui::vec2(200., 100.))),
+//!             ..Default::default()
+//!         };
+//!         let full_output = ctx.run(raw_input, |ctx| {
+//!             egui::CentralPanel::default().show(ctx, |ui| ui.label("Hello"));
+//!         });
+//!         let primitives = ctx.tessellate(full_output.shapes, full_output.pixels_per_point);
+//!         server.send_frame(
+//!             ctx.screen_rect(),
+//!             full_output.pixels_per_point,
+//!             &full_output.textures_delta,
+//!             &primitives,
+//!         );
+//!     }
+//!     events = server.take_events();
+//!     std::thread::sleep(std::time::Duration::from_millis(10));
+//! }
+//!
+//! assert_eq!(events, [egui::Event::Text("hi".to_owned())]);
+//! let fill = ctx.style().visuals.panel_fill;
+//! assert_eq!(viewer.join().unwrap(), fill.to_array());
+//! ```
+
+use crate::{key_from_speedy2d, modifiers_from_speedy2d, pos2_from_speedy2d, Painter, Transform};
+use egui::{
+    epaint::{ImageDelta, Mesh, Vertex},
+    ClippedPrimitive, Color32, ColorImage, Event, FontImage, ImageData, MouseWheelUnit, Pos2, Rect,
+    TextureId, TextureOptions, TexturesDelta,
+};
+use speedy2d::{
+    color::Color,
+    dimen::{UVec2, Vec2},
+    image::ImageHandle,
+    window::{
+        KeyScancode, ModifiersState, MouseButton, MouseScrollDistance, VirtualKeyCode,
+        WindowHandler, WindowHelper, WindowStartupInfo,
+    },
+    Graphics2D,
+};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+
+/// Largest frame accepted by a [`MirrorClient`], to fail cleanly on garbage.
+/// Frames are read as they arrive, so a bad length allocates no more than
+/// what was actually received.
+const MAX_FRAME_BYTES: usize = 1 << 28;
+
+/// Points scrolled for each line of a mouse wheel, as in egui's winit
+/// integration.
+const POINTS_PER_LINE: f32 = 50.;
+
+/// Bytes of frames waiting to be written to a viewer before it is dropped as
+/// stalled. The first frame of a viewer, which carries every texture alive,
+/// doesn't count.
+const VIEWER_BACKLOG_BYTES: usize = 64 << 20;
+
+/// Sends egui frames to the viewers connected to it.
+///
+/// [`WindowWrapper::with_mirror_server`](crate::WindowWrapper::with_mirror_server)
+/// sends every frame it draws, and feeds egui the events sent back by the
+/// viewers. Without a window, call [`MirrorServer::send_frame`] and
+/// [`MirrorServer::take_events`] around each frame instead.
+///
+/// Viewers connecting late receive every texture alive with their first
+/// frame. To do so the server keeps a CPU copy of every texture egui manages,
+/// from the moment it is created and whether viewers are connected or not,
+/// on top of the copies kept by the [`CpuCopyPolicy`](crate::CpuCopyPolicy).
+/// Only run a mirror server when it may be used.
+///
+/// Each viewer is written to by its own thread, so a slow one doesn't hold
+/// the application back. A viewer falling too far behind is disconnected,
+/// since it would miss texture changes if frames were skipped, and can
+/// connect again.
+pub struct MirrorServer {
+    local_addr: SocketAddr,
+    new_viewers: Receiver<TcpStream>,
+    viewers: Vec<Viewer>,
+    events: Receiver<Event>,
+    events_sender: Sender<Event>,
+    /// The textures alive, to send to new viewers.
+    textures: HashMap<TextureId, ImageDelta>,
+}
+
+impl MirrorServer {
+    /// Listens for viewers on the given address, for example
+    /// `"0.0.0.0:7071"` to accept viewers from other machines.
+    ///
+    /// The frames are sent unencrypted and the viewers can control the UI, so
+    /// only listen on trusted networks.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, new_viewers) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if sender.send(stream).is_err() {
+                    return;
+                }
+            }
+        });
+        let (events_sender, events) = mpsc::channel();
+        Ok(Self {
+            local_addr,
+            new_viewers,
+            viewers: vec![],
+            events,
+            events_sender,
+            textures: Default::default(),
+        })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the number of viewers connected, as of the last frame sent.
+    pub fn viewer_count(&self) -> usize {
+        self.viewers.len()
+    }
+
+    /// Sends a frame to every viewer.
+    ///
+    /// `screen_rect` and `pixels_per_point` are the ones of the egui context
+    /// the frame comes from.
+    pub fn send_frame(
+        &mut self,
+        screen_rect: Rect,
+        pixels_per_point: f32,
+        textures_delta: &TexturesDelta,
+        primitives: &[ClippedPrimitive],
+    ) {
+        let mut new_viewers = vec![];
+        while let Ok(stream) = self.new_viewers.try_recv() {
+            match self.add_viewer(stream) {
+                Ok(viewer) => new_viewers.push(viewer),
+                Err(error) => log::warn!("mirror viewer dropped: {error}"),
+            }
+        }
+        // new viewers get every texture alive along with their first frame
+        let first_message = (!new_viewers.is_empty()).then(|| {
+            let mut all_textures = TexturesDelta {
+                set: self
+                    .textures
+                    .iter()
+                    .map(|(id, delta)| (*id, delta.clone()))
+                    .collect(),
+                free: vec![],
+            };
+            all_textures.append(textures_delta.clone());
+            Arc::new(encode_frame(
+                screen_rect,
+                pixels_per_point,
+                &all_textures,
+                primitives,
+            ))
+        });
+
+        for (id, delta) in &textures_delta.set {
+            match delta.pos {
+                None => {
+                    self.textures.insert(*id, delta.clone());
+                }
+                Some(pos) => match self.textures.get_mut(id) {
+                    Some(texture) => patch(&mut texture.image, &delta.image, pos),
+                    None => log::warn!("mirror: partial update of unknown texture {id:?}"),
+                },
+            }
+        }
+        for id in &textures_delta.free {
+            self.textures.remove(id);
+        }
+
+        if !self.viewers.is_empty() {
+            let message = Arc::new(encode_frame(
+                screen_rect,
+                pixels_per_point,
+                textures_delta,
+                primitives,
+            ));
+            self.viewers.retain(|viewer| viewer.send(message.clone()));
+        }
+        if let Some(message) = first_message {
+            for viewer in new_viewers {
+                if viewer.send_first(message.clone()) {
+                    self.viewers.push(viewer);
+                }
+            }
+        }
+    }
+
+    /// Takes the events sent by the viewers since the last call.
+    pub fn take_events(&self) -> Vec<Event> {
+        self.events.try_iter().collect()
+    }
+
+    /// Starts the threads serving a new viewer.
+    fn add_viewer(&self, stream: TcpStream) -> io::Result<Viewer> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let events = self.events_sender.clone();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                match serde_json::from_str::<Vec<Event>>(&line) {
+                    Ok(received) => {
+                        for event in received {
+                            if events.send(event).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(error) => log::warn!("mirror: invalid events from viewer: {error}"),
+                }
+            }
+        });
+
+        let (sender, messages) = mpsc::channel::<(Arc<Vec<u8>>, usize)>();
+        let backlog = Arc::new(AtomicUsize::new(0));
+        let mut writer = stream.try_clone()?;
+        thread::spawn({
+            let backlog = backlog.clone();
+            move || {
+                for (message, counted) in messages {
+                    if writer.write_all(&message).is_err() {
+                        return;
+                    }
+                    backlog.fetch_sub(counted, Ordering::Relaxed);
+                }
+            }
+        });
+        Ok(Viewer {
+            sender,
+            backlog,
+            stream,
+        })
+    }
+}
+
+/// A viewer connected to a [`MirrorServer`].
+struct Viewer {
+    /// The messages to write, with the bytes they count in the backlog.
+    sender: Sender<(Arc<Vec<u8>>, usize)>,
+    /// Bytes queued and not written yet.
+    backlog: Arc<AtomicUsize>,
+    stream: TcpStream,
+}
+
+impl Viewer {
+    /// Queues the first message of the viewer, however long it takes to
+    /// write, and returns whether the viewer is still connected.
+    fn send_first(&self, message: Arc<Vec<u8>>) -> bool {
+        self.sender.send((message, 0)).is_ok()
+    }
+
+    /// Queues a message to write, and returns whether the viewer is still
+    /// connected.
+    fn send(&self, message: Arc<Vec<u8>>) -> bool {
+        let len = message.len();
+        let backlog = self.backlog.fetch_add(len, Ordering::Relaxed) + len;
+        if backlog > VIEWER_BACKLOG_BYTES {
+            log::warn!("mirror viewer dropped: more than {VIEWER_BACKLOG_BYTES} bytes behind");
+            return false;
+        }
+        self.sender.send((message, len)).is_ok()
+    }
+}
+
+impl Drop for Viewer {
+    fn drop(&mut self) {
+        // unblocks the threads serving the viewer, and tells it it was dropped
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// Copies `update` into `image` at `pos`.
+fn patch(image: &mut ImageData, update: &ImageData, pos: [usize; 2]) {
+    match (image, update) {
+        (ImageData::Color(image), ImageData::Color(update)) => {
+            let image = Arc::make_mut(image);
+            patch_pixels(
+                &mut image.pixels,
+                image.size,
+                &update.pixels,
+                update.size,
+                pos,
+            );
+        }
+        (ImageData::Font(image), ImageData::Font(update)) => {
+            patch_pixels(
+                &mut image.pixels,
+                image.size,
+                &update.pixels,
+                update.size,
+                pos,
+            );
+        }
+        _ => log::warn!("mirror: partial update of another image type ignored"),
+    }
+}
+
+fn patch_pixels<T: Copy>(
+    pixels: &mut [T],
+    size: [usize; 2],
+    update: &[T],
+    update_size: [usize; 2],
+    pos: [usize; 2],
+) {
+    let width = update_size[0].min(size[0].saturating_sub(pos[0]));
+    for y in 0..update_size[1].min(size[1].saturating_sub(pos[1])) {
+        let start = (pos[1] + y) * size[0] + pos[0];
+        pixels[start..start + width]
+            .copy_from_slice(&update[y * update_size[0]..y * update_size[0] + width]);
+    }
+}
+
+/// A frame received from a [`MirrorServer`].
+#[derive(Clone, Debug)]
+pub struct MirrorFrame {
+    /// The screen of the egui context the frame comes from.
+    pub screen_rect: Rect,
+    pub pixels_per_point: f32,
+    pub textures_delta: TexturesDelta,
+    /// The tessellated meshes. Paint callbacks are not sent.
+    pub primitives: Vec<ClippedPrimitive>,
+}
+
+/// Receives frames from a [`MirrorServer`], and sends input events back.
+pub struct MirrorClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl MirrorClient {
+    /// Connects to a server.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    /// Waits for the next frame.
+    ///
+    /// Every frame must be painted, or at least have its texture changes
+    /// applied, for the textures to stay in sync with the server.
+    pub fn recv_frame(&mut self) -> io::Result<MirrorFrame> {
+        read_frame(&mut self.reader)
+    }
+
+    /// Sends input events to the server, to be given to egui on its next
+    /// frame. Positions are in the points of the server's context.
+    pub fn send_events(&mut self, events: &[Event]) -> io::Result<()> {
+        send_events(&mut self.writer, events)
+    }
+
+    /// Splits the client into a thread receiving the frames and a handle to
+    /// send events with.
+    fn spawn_reader(self) -> (Receiver<io::Result<MirrorFrame>>, TcpStream) {
+        let (sender, frames) = mpsc::channel();
+        let mut reader = self.reader;
+        thread::spawn(move || loop {
+            let frame = read_frame(&mut reader);
+            let failed = frame.is_err();
+            if sender.send(frame).is_err() || failed {
+                return;
+            }
+        });
+        (frames, self.writer)
+    }
+}
+
+fn send_events(writer: &mut impl Write, events: &[Event]) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, events)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// A window showing the frames of a [`MirrorServer`], and sending it the
+/// window's input. It is what the `egui_speedy2d_viewer` binary runs.
+///
+/// ```no_run
+/// use egui_speedy2d::mirror::MirrorViewer;
+///
+/// let viewer = MirrorViewer::connect("127.0.0.1:7071").unwrap();
+/// let window = speedy2d::Window::new_centered("Viewer", (640, 480)).unwrap();
+/// window.run_loop(viewer)
+/// ```
+pub struct MirrorViewer {
+    frames: Receiver<io::Result<MirrorFrame>>,
+    writer: TcpStream,
+    painter: Painter<ImageHandle>,
+    /// The primitives of the last frame, drawn again until the next one.
+    primitives: Vec<ClippedPrimitive>,
+    screen_size: Option<UVec2>,
+    events: Vec<Event>,
+    mouse_position: Vec2,
+    modifiers: ModifiersState,
+}
+
+impl MirrorViewer {
+    /// Connects to a server.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let (frames, writer) = MirrorClient::connect(addr)?.spawn_reader();
+        Ok(Self {
+            frames,
+            writer,
+            painter: Default::default(),
+            primitives: vec![],
+            screen_size: None,
+            events: vec![],
+            mouse_position: Vec2::ZERO,
+            modifiers: Default::default(),
+        })
+    }
+
+    fn modifiers(&self) -> egui::Modifiers {
+        modifiers_from_speedy2d(&self.modifiers)
+    }
+
+    fn button_event(&mut self, button: MouseButton, pressed: bool) {
+        let button = match button {
+            MouseButton::Left => egui::PointerButton::Primary,
+            MouseButton::Right => egui::PointerButton::Secondary,
+            MouseButton::Middle => egui::PointerButton::Middle,
+            MouseButton::Other(_) => return,
+        };
+        self.events.push(Event::PointerButton {
+            pos: pos2_from_speedy2d(self.mouse_position),
+            button,
+            pressed,
+            modifiers: self.modifiers(),
+        });
+    }
+
+    fn key_event(&mut self, virtual_key_code: Option<VirtualKeyCode>, pressed: bool) {
+        if let Some(key) = key_from_speedy2d(virtual_key_code) {
+            self.events.push(Event::Key {
+                key,
+                physical_key: None,
+                pressed,
+                repeat: false,
+                modifiers: self.modifiers(),
+            });
+        }
+    }
+}
+
+impl<UserEventType> WindowHandler<UserEventType> for MirrorViewer {
+    fn on_start(&mut self, helper: &mut WindowHelper<UserEventType>, _info: WindowStartupInfo) {
+        helper.request_redraw();
+    }
+
+    fn on_draw(&mut self, helper: &mut WindowHelper<UserEventType>, graphics: &mut Graphics2D) {
+        let mut textures_delta = TexturesDelta::default();
+        loop {
+            match self.frames.try_recv() {
+                Ok(Ok(frame)) => {
+                    // apply the texture changes of skipped frames in order
+                    if !textures_delta.is_empty() {
+                        let textures_delta = std::mem::take(&mut textures_delta);
+                        for error in self.painter.paint_primitives(
+                            textures_delta,
+                            vec![],
+                            Transform::IDENTITY,
+                            graphics,
+                        ) {
+                            log::error!("{error}");
+                        }
+                    }
+                    textures_delta = frame.textures_delta;
+                    self.primitives = frame.primitives;
+                    // egui points are painted as pixels, on the server too
+                    let size = frame.screen_rect.size();
+                    let size = UVec2::new(size.x.round() as u32, size.y.round() as u32);
+                    if self.screen_size != Some(size) {
+                        helper.set_size_pixels(size);
+                        self.screen_size = Some(size);
+                    }
+                }
+                Ok(Err(error)) => {
+                    log::error!("mirror server disconnected: {error}");
+                    helper.terminate_loop();
+                    return;
+                }
+                Err(_) => break,
+            }
+        }
+
+        graphics.clear_screen(Color::from_gray(0.1));
+        for error in self.painter.paint_primitives(
+            textures_delta,
+            self.primitives.clone(),
+            Transform::IDENTITY,
+            graphics,
+        ) {
+            log::error!("{error}");
+        }
+
+        if !self.events.is_empty() {
+            let events = std::mem::take(&mut self.events);
+            if let Err(error) = send_events(&mut self.writer, &events) {
+                log::error!("mirror server disconnected: {error}");
+                helper.terminate_loop();
+                return;
+            }
+        }
+        helper.request_redraw();
+    }
+
+    fn on_mouse_move(&mut self, _helper: &mut WindowHelper<UserEventType>, position: Vec2) {
+        self.mouse_position = position;
+        self.events
+            .push(Event::PointerMoved(pos2_from_speedy2d(position)));
+    }
+
+    fn on_mouse_button_down(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        button: MouseButton,
+    ) {
+        self.button_event(button, true);
+    }
+
+    fn on_mouse_button_up(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        button: MouseButton,
+    ) {
+        self.button_event(button, false);
+    }
+
+    fn on_mouse_wheel_scroll(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        distance: MouseScrollDistance,
+    ) {
+        let (unit, delta, points) = match distance {
+            MouseScrollDistance::Lines { x, y, .. } => (
+                MouseWheelUnit::Line,
+                egui::vec2(x as f32, y as f32),
+                POINTS_PER_LINE,
+            ),
+            MouseScrollDistance::Pixels { x, y, .. } => {
+                (MouseWheelUnit::Point, egui::vec2(x as f32, y as f32), 1.)
+            }
+            MouseScrollDistance::Pages { x, y, .. } => {
+                let page = self.screen_size.map_or(0., |size| size.y as f32);
+                (MouseWheelUnit::Page, egui::vec2(x as f32, y as f32), page)
+            }
+        };
+        self.events.push(Event::MouseWheel {
+            unit,
+            delta,
+            modifiers: self.modifiers(),
+        });
+        // egui only scrolls on `Scroll` events
+        self.events.push(Event::Scroll(delta * points));
+    }
+
+    fn on_key_down(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        virtual_key_code: Option<VirtualKeyCode>,
+        _scancode: KeyScancode,
+    ) {
+        self.key_event(virtual_key_code, true);
+    }
+
+    fn on_key_up(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        virtual_key_code: Option<VirtualKeyCode>,
+        _scancode: KeyScancode,
+    ) {
+        self.key_event(virtual_key_code, false);
+    }
+
+    fn on_keyboard_char(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        unicode_codepoint: char,
+    ) {
+        self.events.push(Event::Text(unicode_codepoint.to_string()));
+    }
+
+    fn on_keyboard_modifiers_changed(
+        &mut self,
+        _helper: &mut WindowHelper<UserEventType>,
+        state: ModifiersState,
+    ) {
+        self.modifiers = state;
+    }
+}
+
+// Frames are encoded as a little endian `u32` byte length, followed by the
+// screen, the texture changes and the meshes.
+
+fn encode_frame(
+    screen_rect: Rect,
+    pixels_per_point: f32,
+    textures_delta: &TexturesDelta,
+    primitives: &[ClippedPrimitive],
+) -> Vec<u8> {
+    let mut e = Encoder(vec![0; 4]);
+    e.rect(screen_rect);
+    e.f32(pixels_per_point);
+
+    e.len(textures_delta.set.len());
+    for (id, delta) in &textures_delta.set {
+        e.texture_id(*id);
+        e.image_delta(delta);
+    }
+    e.len(textures_delta.free.len());
+    for id in &textures_delta.free {
+        e.texture_id(*id);
+    }
+
+    let meshes = primitives
+        .iter()
+        .filter_map(|primitive| match &primitive.primitive {
+            egui::epaint::Primitive::Mesh(mesh) => Some((primitive.clip_rect, mesh)),
+            egui::epaint::Primitive::Callback(_) => None,
+        })
+        .collect::<Vec<_>>();
+    e.len(meshes.len());
+    for (clip_rect, mesh) in meshes {
+        e.rect(clip_rect);
+        e.mesh(mesh);
+    }
+
+    let mut bytes = e.0;
+    let len = (bytes.len() - 4) as u32;
+    bytes[..4].copy_from_slice(&len.to_le_bytes());
+    bytes
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<MirrorFrame> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_BYTES {
+        return Err(invalid_data(format!("frame of {len} bytes")));
+    }
+    // grown as the bytes come, rather than trusting the length up front
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let mut d = Decoder(&bytes);
+
+    let screen_rect = d.rect()?;
+    let pixels_per_point = d.f32()?;
+    let mut textures_delta = TexturesDelta::default();
+    for _ in 0..d.len()? {
+        textures_delta.set.push((d.texture_id()?, d.image_delta()?));
+    }
+    for _ in 0..d.len()? {
+        textures_delta.free.push(d.texture_id()?);
+    }
+    let mut primitives = vec![];
+    for _ in 0..d.len()? {
+        primitives.push(ClippedPrimitive {
+            clip_rect: d.rect()?,
+            primitive: egui::epaint::Primitive::Mesh(d.mesh()?),
+        });
+    }
+    Ok(MirrorFrame {
+        screen_rect,
+        pixels_per_point,
+        textures_delta,
+        primitives,
+    })
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn pos(&mut self, pos: Pos2) {
+        self.f32(pos.x);
+        self.f32(pos.y);
+    }
+
+    fn rect(&mut self, rect: Rect) {
+        self.pos(rect.min);
+        self.pos(rect.max);
+    }
+
+    fn texture_id(&mut self, id: TextureId) {
+        match id {
+            TextureId::Managed(id) => {
+                self.u8(0);
+                self.u64(id);
+            }
+            TextureId::User(id) => {
+                self.u8(1);
+                self.u64(id);
+            }
+        }
+    }
+
+    fn image_delta(&mut self, delta: &ImageDelta) {
+        let filter = |filter| match filter {
+            egui::TextureFilter::Nearest => 0,
+            egui::TextureFilter::Linear => 1,
+        };
+        self.u8(filter(delta.options.magnification));
+        self.u8(filter(delta.options.minification));
+        self.u8(match delta.options.wrap_mode {
+            egui::TextureWrapMode::ClampToEdge => 0,
+            egui::TextureWrapMode::Repeat => 1,
+            egui::TextureWrapMode::MirroredRepeat => 2,
+        });
+        match delta.pos {
+            None => self.u8(0),
+            Some(pos) => {
+                self.u8(1);
+                self.len(pos[0]);
+                self.len(pos[1]);
+            }
+        }
+        match &delta.image {
+            ImageData::Color(image) => {
+                self.u8(0);
+                self.len(image.size[0]);
+                self.len(image.size[1]);
+                for pixel in &image.pixels {
+                    self.0.extend_from_slice(&pixel.to_array());
+                }
+            }
+            ImageData::Font(image) => {
+                self.u8(1);
+                self.len(image.size[0]);
+                self.len(image.size[1]);
+                for coverage in &image.pixels {
+                    self.f32(*coverage);
+                }
+            }
+        }
+    }
+
+    fn mesh(&mut self, mesh: &Mesh) {
+        self.texture_id(mesh.texture_id);
+        self.len(mesh.indices.len());
+        for index in &mesh.indices {
+            self.u32(*index);
+        }
+        self.len(mesh.vertices.len());
+        for vertex in &mesh.vertices {
+            self.pos(vertex.pos);
+            self.pos(vertex.uv);
+            self.0.extend_from_slice(&vertex.color.to_array());
+        }
+    }
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        if self.0.len() < N {
+            return Err(invalid_data("truncated frame".to_owned()));
+        }
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    /// Reads a length, checking that the rest of the frame can hold that many
+    /// items of at least `min_item_size` bytes.
+    fn len_of(&mut self, min_item_size: usize) -> io::Result<usize> {
+        let len = self.u32()? as usize;
+        if len.saturating_mul(min_item_size) > self.0.len() {
+            return Err(invalid_data(format!(
+                "{len} items past the end of the frame"
+            )));
+        }
+        Ok(len)
+    }
+
+    fn len(&mut self) -> io::Result<usize> {
+        self.len_of(1)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.bytes()?))
+    }
+
+    fn pos(&mut self) -> io::Result<Pos2> {
+        Ok(Pos2::new(self.f32()?, self.f32()?))
+    }
+
+    fn rect(&mut self) -> io::Result<Rect> {
+        Ok(Rect::from_min_max(self.pos()?, self.pos()?))
+    }
+
+    fn texture_id(&mut self) -> io::Result<TextureId> {
+        match self.u8()? {
+            0 => Ok(TextureId::Managed(self.u64()?)),
+            1 => Ok(TextureId::User(self.u64()?)),
+            tag => Err(invalid_data(format!("invalid texture id tag {tag}"))),
+        }
+    }
+
+    fn image_delta(&mut self) -> io::Result<ImageDelta> {
+        let mut filter = || match self.u8()? {
+            0 => Ok(egui::TextureFilter::Nearest),
+            1 => Ok(egui::TextureFilter::Linear),
+            tag => Err(invalid_data(format!("invalid texture filter {tag}"))),
+        };
+        let magnification = filter()?;
+        let minification = filter()?;
+        let wrap_mode = match self.u8()? {
+            0 => egui::TextureWrapMode::ClampToEdge,
+            1 => egui::TextureWrapMode::Repeat,
+            2 => egui::TextureWrapMode::MirroredRepeat,
+            tag => return Err(invalid_data(format!("invalid texture wrap mode {tag}"))),
+        };
+        let options = TextureOptions {
+            magnification,
+            minification,
+            wrap_mode,
+        };
+        let pos = match self.u8()? {
+            0 => None,
+            _ => Some([self.u32()? as usize, self.u32()? as usize]),
+        };
+        let tag = self.u8()?;
+        let size = [self.u32()? as usize, self.u32()? as usize];
+        let count = size[0]
+            .checked_mul(size[1])
+            .ok_or_else(|| invalid_data(format!("invalid image size {size:?}")))?;
+        if count.saturating_mul(4) > self.0.len() {
+            return Err(invalid_data("truncated image".to_owned()));
+        }
+        let image = match tag {
+            0 => {
+                let mut pixels = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let [r, g, b, a] = self.bytes()?;
+                    pixels.push(Color32::from_rgba_premultiplied(r, g, b, a));
+                }
+                ImageData::Color(Arc::new(ColorImage { size, pixels }))
+            }
+            1 => {
+                let mut pixels = Vec::with_capacity(count);
+                for _ in 0..count {
+                    pixels.push(self.f32()?);
+                }
+                ImageData::Font(FontImage { size, pixels })
+            }
+            tag => return Err(invalid_data(format!("invalid image tag {tag}"))),
+        };
+        Ok(ImageDelta {
+            image,
+            options,
+            pos,
+        })
+    }
+
+    fn mesh(&mut self) -> io::Result<Mesh> {
+        let texture_id = self.texture_id()?;
+        let len = self.len_of(4)?;
+        let mut indices = Vec::with_capacity(len);
+        for _ in 0..len {
+            indices.push(self.u32()?);
+        }
+        let len = self.len_of(20)?;
+        let mut vertices = Vec::with_capacity(len);
+        for _ in 0..len {
+            vertices.push(Vertex {
+                pos: self.pos()?,
+                uv: self.pos()?,
+                color: {
+                    let [r, g, b, a] = self.bytes()?;
+                    Color32::from_rgba_premultiplied(r, g, b, a)
+                },
+            });
+        }
+        Ok(Mesh {
+            indices,
+            vertices,
+            texture_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let frame = encode_frame(Rect::ZERO, 1., &Default::default(), &[]);
+        assert!(read_frame(&mut frame.as_slice()).is_ok());
+
+        let error = read_frame(&mut &frame[..frame.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // a length claiming more than received
+        let mut garbage = (MAX_FRAME_BYTES as u32).to_le_bytes().to_vec();
+        garbage.extend_from_slice(&frame[4..]);
+        let error = read_frame(&mut garbage.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn the_first_frame_does_not_count_in_the_backlog() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        // nothing is written: the messages stay queued
+        let (sender, _messages) = mpsc::channel();
+        let viewer = Viewer {
+            sender,
+            backlog: Default::default(),
+            stream,
+        };
+
+        assert!(viewer.send_first(Arc::new(vec![0; VIEWER_BACKLOG_BYTES + 1])));
+        assert!(viewer.send(Arc::new(vec![0; VIEWER_BACKLOG_BYTES / 2])));
+        assert!(viewer.send(Arc::new(vec![0; VIEWER_BACKLOG_BYTES / 2])));
+        assert!(!viewer.send(Arc::new(vec![0; 1])));
+    }
+}
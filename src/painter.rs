@@ -0,0 +1,974 @@
+//! Conversion of egui meshes and textures into renderer calls.
+
+use crate::{
+    cursor, profiling::profile_scope, rect_from_egui, smoothing_mode_from_egui, streaming,
+    textures, triangle_colors, unmultiply, vec2_from_egui, wrap, ContextTransformExt, Renderer,
+    Transform, WindowWrapperError,
+};
+use speedy2d::{dimen::UVec2, image::ImageSmoothingMode};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+/// The user texture ids left for [`Painter::set_user_texture`] to pick from.
+///
+/// User texture ids below them are handed out by the painter, and the ones
+/// above by [`ContextTexturesExt`](crate::ContextTexturesExt) and
+/// [`StreamingTexture`](crate::StreamingTexture).
+pub const CHOSEN_USER_TEXTURE_IDS: Range<u64> = 1 << 60..1 << 61;
+
+/// Draws egui frames through a [`Renderer`], and keeps track of the textures
+/// egui asks for.
+///
+/// [`WindowWrapper`](crate::WindowWrapper) uses one to draw to the window. Use
+/// one directly to draw egui frames with another renderer, for example a
+/// [`SoftwareRenderer`](crate::SoftwareRenderer):
+///
+/// ```
+/// use egui_speedy2d::{Painter, SoftwareRenderer};
+///
+/// let ctx = egui::Context::default();
+/// let mut renderer = SoftwareRenderer::new((200, 100));
+/// let mut painter = Painter::default();
+///
+/// let full_output = ctx.run(Default::default(), |ctx| {
+///     egui::CentralPanel::default().show(ctx, |ui| ui.label("Hello"));
+/// });
+/// let errors = painter.paint(&ctx, full_output, &mut renderer);
+/// assert!(errors.is_empty());
+///
+/// // the central panel is filled with the panel color
+/// let fill = ctx.style().visuals.panel_fill;
+/// assert_eq!(renderer.pixel(190, 90), fill.to_array());
+/// ```
+pub struct Painter<I> {
+    id_and_textures: HashMap<u64, Texture<I>>,
+    user_textures: HashMap<u64, Texture<I>>,
+    next_user_id: u64,
+    pending_user_textures: Vec<(u64, PendingTexture)>,
+    to_free_textures: Vec<u64>,
+    cpu_copy_policy: CpuCopyPolicy,
+    cpu_copy_overrides: HashMap<u64, bool>,
+    fallback_texture: Option<I>,
+    /// Missing textures already reported.
+    missing_textures: HashSet<egui::TextureId>,
+    /// Textures whose partial updates without a CPU copy were already
+    /// reported, until they are uploaded whole again.
+    partial_update_textures: HashSet<u64>,
+    /// Textures already warned about for mixing filters.
+    mixed_filter_textures: HashSet<egui::TextureId>,
+    /// Textures already warned about for being repeated too many times to be
+    /// split.
+    over_repeated_textures: HashSet<egui::TextureId>,
+    /// The texture registry of the context this painter draws, whose images
+    /// are forgotten with the painter.
+    pub(crate) texture_registry: Option<u64>,
+}
+
+impl<I> Default for Painter<I> {
+    fn default() -> Self {
+        Self {
+            id_and_textures: Default::default(),
+            user_textures: Default::default(),
+            next_user_id: 0,
+            pending_user_textures: Default::default(),
+            to_free_textures: Default::default(),
+            cpu_copy_policy: Default::default(),
+            cpu_copy_overrides: Default::default(),
+            fallback_texture: None,
+            missing_textures: Default::default(),
+            partial_update_textures: Default::default(),
+            mixed_filter_textures: Default::default(),
+            over_repeated_textures: Default::default(),
+            texture_registry: None,
+        }
+    }
+}
+
+impl<I> Drop for Painter<I> {
+    fn drop(&mut self) {
+        if let Some(key) = self.texture_registry {
+            textures::forget_registry(key);
+        }
+    }
+}
+
+impl<I: Clone> Painter<I> {
+    /// Sets which textures keep a copy of their pixels in CPU memory.
+    pub fn set_cpu_copy_policy(&mut self, policy: CpuCopyPolicy) {
+        self.cpu_copy_policy = policy;
+    }
+
+    /// Overrides the [`CpuCopyPolicy`] for a single texture.
+    ///
+    /// Turning the copy off drops it right away. Turning it on takes effect
+    /// the next time egui uploads the whole texture.
+    pub fn set_texture_cpu_copy(&mut self, texture_id: egui::TextureId, keep: bool) {
+        let egui::TextureId::Managed(id) = texture_id else {
+            return;
+        };
+        self.cpu_copy_overrides.insert(id, keep);
+        if !keep {
+            if let Some(texture) = self.id_and_textures.get_mut(&id) {
+                texture.cpu_copy = None;
+            }
+        }
+    }
+
+    /// Returns the textures alive, egui's first, sorted by id.
+    pub(crate) fn textures(&self) -> Vec<TextureInfo<'_, I>> {
+        let managed = self
+            .id_and_textures
+            .iter()
+            .map(|(id, texture)| (egui::TextureId::Managed(*id), texture));
+        let user = self
+            .user_textures
+            .iter()
+            .map(|(id, texture)| (egui::TextureId::User(*id), texture));
+        let mut textures = managed
+            .chain(user)
+            .map(|(id, texture)| TextureInfo {
+                id,
+                handle: &texture.handle,
+                size: texture.size,
+                name: texture.name.as_deref(),
+                options: texture.options,
+                cpu_copy: texture.cpu_copy.is_some(),
+            })
+            .collect::<Vec<_>>();
+        textures.sort_by_key(|texture| match texture.id {
+            egui::TextureId::Managed(id) => (0, id),
+            egui::TextureId::User(id) => (1, id),
+        });
+        textures
+    }
+
+    /// Returns statistics about the textures currently alive.
+    pub fn texture_stats(&self) -> TextureStats {
+        let mut stats = TextureStats::default();
+        for texture in self
+            .id_and_textures
+            .values()
+            .chain(self.user_textures.values())
+        {
+            stats.count += 1;
+            stats.gpu_bytes += texture.size.0 * texture.size.1 * 4;
+            stats.cpu_bytes += texture.cpu_copy.as_ref().map_or(0, |i| i.pixels.len());
+        }
+        stats
+    }
+
+    /// Uploads `image` as the user texture `id`, replacing the previous one,
+    /// and returns the [`egui::TextureId`] to paint it with.
+    ///
+    /// Unlike the textures managed by egui, user textures stay alive until
+    /// [`Painter::free_user_texture`] is called.
+    ///
+    /// `id` is either one handed out by the painter, to replace its texture,
+    /// or one in [`CHOSEN_USER_TEXTURE_IDS`]. Other ids are rejected with
+    /// [`WindowWrapperError::ReservedTextureId`], since the painter or the
+    /// egui context would hand them out later.
+    ///
+    /// ```
+    /// use egui_speedy2d::{Painter, SoftwareRenderer, WindowWrapperError, CHOSEN_USER_TEXTURE_IDS};
+    ///
+    /// let mut renderer = SoftwareRenderer::new((100, 100));
+    /// let mut painter = Painter::default();
+    /// let image = || egui::ColorImage::new([1, 1], egui::Color32::RED);
+    ///
+    /// let chosen = CHOSEN_USER_TEXTURE_IDS.start;
+    /// assert!(painter.set_user_texture(&mut renderer, chosen, image(), Default::default()).is_ok());
+    /// let reserved = painter.set_user_texture(&mut renderer, 0, image(), Default::default());
+    /// assert!(matches!(reserved, Err(WindowWrapperError::ReservedTextureId(0))));
+    ///
+    /// let added = painter.add_user_texture(&mut renderer, image(), Default::default()).unwrap();
+    /// assert_eq!(added, egui::TextureId::User(0));
+    /// assert!(painter.set_user_texture(&mut renderer, 0, image(), Default::default()).is_ok());
+    /// ```
+    pub fn set_user_texture<R: Renderer<Image = I>>(
+        &mut self,
+        renderer: &mut R,
+        id: u64,
+        image: impl Into<egui::ImageData>,
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError> {
+        if id >= self.next_user_id && !CHOSEN_USER_TEXTURE_IDS.contains(&id) {
+            return Err(WindowWrapperError::ReservedTextureId(id));
+        }
+        let image = RgbaImage::from(image.into());
+        self.upload_user_texture(renderer, id, None, image.size, &image.pixels, options)
+    }
+
+    /// Uploads `image` as a new user texture, with an id that isn't used by
+    /// any other, and returns the [`egui::TextureId`] to paint it with.
+    pub fn add_user_texture<R: Renderer<Image = I>>(
+        &mut self,
+        renderer: &mut R,
+        image: impl Into<egui::ImageData>,
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError> {
+        let id = self.next_user_id;
+        let image = RgbaImage::from(image.into());
+        let texture_id =
+            self.upload_user_texture(renderer, id, None, image.size, &image.pixels, options)?;
+        self.next_user_id += 1;
+        Ok(texture_id)
+    }
+
+    /// Registers an image already created by the renderer as a new user
+    /// texture, and returns the [`egui::TextureId`] to paint it with.
+    ///
+    /// ```
+    /// use egui_speedy2d::{Painter, Renderer, SoftwareRenderer};
+    /// use speedy2d::image::ImageSmoothingMode;
+    ///
+    /// let mut renderer = SoftwareRenderer::new((100, 100));
+    /// let mut painter = Painter::default();
+    /// let sprite = renderer
+    ///     .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[0, 0, 255, 255])
+    ///     .unwrap();
+    /// let sprite = painter.insert_user_image(sprite, (1, 1).into(), Default::default());
+    /// let overlay = painter
+    ///     .load_user_texture_rgba("overlay", [1, 1], vec![0, 255, 0, 255], Default::default())
+    ///     .unwrap();
+    ///
+    /// let ctx = egui::Context::default();
+    /// let full_output = ctx.run(Default::default(), |ctx| {
+    ///     egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
+    ///         ui.image((sprite, egui::vec2(20., 20.)));
+    ///         ui.image((overlay, egui::vec2(20., 20.)));
+    ///     });
+    /// });
+    /// assert!(painter.paint(&ctx, full_output, &mut renderer).is_empty());
+    /// assert_eq!(renderer.pixel(10, 10), [0, 0, 255, 255]);
+    /// assert_eq!(renderer.pixel(10, 40), [0, 255, 0, 255]);
+    /// ```
+    pub fn insert_user_image(
+        &mut self,
+        image: I,
+        size: UVec2,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        let id = self.next_user_id;
+        self.next_user_id += 1;
+        self.insert_user_image_as(id, image, size, options)
+    }
+
+    /// Registers an image created by the renderer as the user texture `id`.
+    pub(crate) fn insert_user_image_as(
+        &mut self,
+        id: u64,
+        image: I,
+        size: UVec2,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        self.user_textures.insert(
+            id,
+            Texture {
+                handle: image,
+                size: (size.x as usize, size.y as usize),
+                cpu_copy: None,
+                options,
+                name: None,
+            },
+        );
+        egui::TextureId::User(id)
+    }
+
+    /// Creates a new user texture from straight (non-premultiplied) RGBA
+    /// pixels, and returns the [`egui::TextureId`] to paint it with.
+    ///
+    /// The pixels are uploaded when the next frame is painted, since no
+    /// renderer is at hand here.
+    ///
+    /// Fails with [`WindowWrapperError::TexturePixels`] if `pixels` isn't
+    /// `size[0] * size[1] * 4` bytes long.
+    pub fn load_user_texture_rgba(
+        &mut self,
+        name: impl Into<String>,
+        size: [usize; 2],
+        pixels: Vec<u8>,
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError> {
+        check_pixels(size, &pixels)?;
+        let id = self.next_user_id;
+        self.next_user_id += 1;
+        Ok(self.load_user_texture_rgba_as(id, name, size, pixels, options))
+    }
+
+    /// Creates the user texture `id` from straight RGBA pixels, uploaded when
+    /// the next frame is painted. The pixels are checked by the caller with
+    /// [`check_pixels`].
+    pub(crate) fn load_user_texture_rgba_as(
+        &mut self,
+        id: u64,
+        name: impl Into<String>,
+        size: [usize; 2],
+        pixels: Vec<u8>,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        self.pending_user_textures.push((
+            id,
+            PendingTexture {
+                name: name.into(),
+                image: RgbaImage {
+                    size: (size[0], size[1]),
+                    pixels,
+                },
+                options,
+            },
+        ));
+        egui::TextureId::User(id)
+    }
+
+    /// Frees the user texture `id`.
+    pub fn free_user_texture(&mut self, id: u64) {
+        if self.user_textures.remove(&id).is_some() {
+            #[cfg(feature = "profiling")]
+            tracing::debug!(texture_id = ?egui::TextureId::User(id), "texture freed");
+        }
+        self.pending_user_textures
+            .retain(|(pending_id, _)| *pending_id != id);
+    }
+
+    /// Draws a finished egui frame of `ctx` with `renderer`.
+    ///
+    /// Problems such as a failed texture upload or a mesh referencing a
+    /// missing texture don't stop the frame: the rest is still drawn, missing
+    /// textures are replaced by a magenta and black checkerboard, and the
+    /// problems are returned.
+    pub fn paint<R: Renderer<Image = I>>(
+        &mut self,
+        ctx: &egui::Context,
+        full_output: egui::FullOutput,
+        renderer: &mut R,
+    ) -> Vec<WindowWrapperError> {
+        let clipped_primitives = ctx.tessellate(full_output.shapes, full_output.pixels_per_point);
+        self.paint_tessellated(
+            ctx,
+            full_output.textures_delta,
+            clipped_primitives,
+            renderer,
+        )
+    }
+
+    /// [`Painter::paint`], for a frame of `ctx` that is already tessellated.
+    pub(crate) fn paint_tessellated<R: Renderer<Image = I>>(
+        &mut self,
+        ctx: &egui::Context,
+        textures_delta: egui::TexturesDelta,
+        clipped_primitives: Vec<egui::ClippedPrimitive>,
+        renderer: &mut R,
+    ) -> Vec<WindowWrapperError> {
+        let mut errors = vec![];
+        streaming::upload(ctx, self, renderer, &clipped_primitives, &mut errors);
+        errors.extend(self.paint_primitives(
+            textures_delta,
+            clipped_primitives,
+            ctx.paint_transform(),
+            renderer,
+        ));
+        errors
+    }
+
+    /// Applies the texture changes of a frame and draws its tessellated
+    /// primitives, for frames that don't come from a local egui context, such
+    /// as frames streamed from another process.
+    ///
+    /// Missing textures and other problems are handled as in
+    /// [`Painter::paint`].
+    pub fn paint_primitives<R: Renderer<Image = I>>(
+        &mut self,
+        textures_delta: egui::TexturesDelta,
+        clipped_primitives: Vec<egui::ClippedPrimitive>,
+        transform: Transform,
+        renderer: &mut R,
+    ) -> Vec<WindowWrapperError> {
+        let mut errors = vec![];
+
+        // free old textures
+        self.free_textures();
+
+        // save textures to delete next frame
+        self.to_free_textures = textures_delta
+            .free
+            .iter()
+            .filter_map(|t| match t {
+                egui::TextureId::Managed(id) => Some(*id),
+                egui::TextureId::User(_) => None,
+            })
+            .collect();
+
+        // set new textures
+        self.set_textures(textures_delta, renderer, &mut errors);
+        for (id, pending) in std::mem::take(&mut self.pending_user_textures) {
+            let PendingTexture {
+                name,
+                image,
+                options,
+            } = pending;
+            if let Err(error) = self.upload_user_texture(
+                renderer,
+                id,
+                Some(name),
+                image.size,
+                &image.pixels,
+                options,
+            ) {
+                errors.push(error);
+            }
+        }
+
+        // draw
+        self.draw_primitives(clipped_primitives, transform, renderer, &mut errors);
+
+        // don't leak the last egui clip rect into the user's drawing
+        renderer.set_clip(None);
+
+        errors
+    }
+
+    /// Draws a vector version of the cursor `icon` with its hotspot at `pos`,
+    /// in window coordinates.
+    ///
+    /// Call it after [`Painter::paint`], which uploads the font atlas the
+    /// cursor is drawn with.
+    ///
+    /// ```
+    /// use egui_speedy2d::{Painter, SoftwareRenderer};
+    ///
+    /// let ctx = egui::Context::default();
+    /// let mut renderer = SoftwareRenderer::new((40, 40));
+    /// let mut painter = Painter::default();
+    /// let full_output = ctx.run(Default::default(), |_| {});
+    /// painter.paint(&ctx, full_output, &mut renderer);
+    ///
+    /// let pos = egui::pos2(20., 20.);
+    /// painter.paint_cursor(&ctx, egui::CursorIcon::ResizeHorizontal, pos, &mut renderer);
+    /// assert_eq!(renderer.pixel(20, 20), [255, 255, 255, 255]);
+    /// assert_eq!(renderer.pixel(20, 5), [0, 0, 0, 0]);
+    /// ```
+    pub fn paint_cursor<R: Renderer<Image = I>>(
+        &mut self,
+        ctx: &egui::Context,
+        icon: egui::CursorIcon,
+        pos: egui::Pos2,
+        renderer: &mut R,
+    ) -> Vec<WindowWrapperError> {
+        let mut errors = vec![];
+        let shapes = cursor::shapes(icon, pos)
+            .into_iter()
+            .map(|shape| epaint::ClippedShape {
+                clip_rect: egui::Rect::from_center_size(pos, egui::vec2(64., 64.)),
+                shape,
+            })
+            .collect();
+        let clipped_primitives = ctx.tessellate(shapes, ctx.pixels_per_point());
+        self.draw_primitives(
+            clipped_primitives,
+            Transform::IDENTITY,
+            renderer,
+            &mut errors,
+        );
+        renderer.set_clip(None);
+        errors
+    }
+
+    /// Draws tessellated egui primitives, moved by `transform`.
+    fn draw_primitives<R: Renderer<Image = I>>(
+        &mut self,
+        clipped_primitives: Vec<egui::ClippedPrimitive>,
+        transform: Transform,
+        renderer: &mut R,
+        errors: &mut Vec<WindowWrapperError>,
+    ) {
+        profile_scope!("draw_meshes");
+        // reused by all the meshes of the frame
+        let mut triangles = vec![];
+        for egui::ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in clipped_primitives
+        {
+            renderer.set_clip(Some(rect_from_egui(transform.apply_rect(clip_rect))));
+            let epaint::Primitive::Mesh(epaint::Mesh {
+                indices,
+                vertices,
+                texture_id,
+            }) = primitive
+            else {
+                errors.push(WindowWrapperError::UnsupportedPaintCallback);
+                continue;
+            };
+
+            let texture = match texture_id {
+                egui::TextureId::Managed(id) => self.id_and_textures.get(&id),
+                egui::TextureId::User(id) => self.user_textures.get(&id),
+            };
+            let (handle, wrap_mode) = match texture {
+                Some(texture) => {
+                    if !self.missing_textures.is_empty() {
+                        self.missing_textures.remove(&texture_id);
+                    }
+                    (texture.handle.clone(), texture.options.wrap_mode)
+                }
+                None => {
+                    // report each missing texture once, not every frame
+                    let first_report = self.missing_textures.insert(texture_id);
+                    if first_report {
+                        errors.push(WindowWrapperError::MissingTexture(texture_id));
+                    }
+                    match self.fallback_texture(renderer) {
+                        Ok(handle) => (handle, egui::TextureWrapMode::ClampToEdge),
+                        Err(error) => {
+                            if first_report {
+                                errors.push(error);
+                            }
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            if indices.len() % 3 != 0 || indices.iter().any(|i| *i as usize >= vertices.len()) {
+                errors.push(WindowWrapperError::InvalidMesh(texture_id));
+            }
+            let mut over_repeated = false;
+            for indices in indices.chunks_exact(3) {
+                let triangle = [0, 1, 2].map(|i| vertices.get(indices[i] as usize).copied());
+                if let [Some(a), Some(b), Some(c)] = triangle {
+                    over_repeated |= !wrap::split_triangle([a, b, c], wrap_mode, &mut triangles);
+                }
+            }
+            // meshes are drawn every frame, warn once per texture
+            if over_repeated && self.over_repeated_textures.insert(texture_id) {
+                log::warn!(
+                    "texture {texture_id:?}: a triangle repeats it over too many tiles, drawing it \
+                     clamped to edge"
+                );
+            }
+            for mut v in triangles.drain(..) {
+                let mut p = v.map(|v| vec2_from_egui(transform.apply(v.pos)));
+
+                // dots must be in clockwise order
+                let cross_product =
+                    (p[1].x - p[0].x) * (p[2].y - p[0].y) - (p[1].y - p[0].y) * (p[2].x - p[0].x);
+                if cross_product.is_sign_positive() {
+                    v.swap(1, 2);
+                    p.swap(1, 2);
+                }
+
+                renderer.draw_triangle(
+                    p,
+                    triangle_colors(v.map(|v| v.color)),
+                    v.map(|v| vec2_from_egui(v.uv)),
+                    &handle,
+                );
+            }
+        }
+    }
+
+    /// Uploads straight RGBA pixels as the user texture `id`.
+    pub(crate) fn upload_user_texture<R: Renderer<Image = I>>(
+        &mut self,
+        renderer: &mut R,
+        id: u64,
+        name: Option<String>,
+        size: (usize, usize),
+        pixels: &[u8],
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError> {
+        profile_scope!("upload_user_texture");
+        let texture_id = egui::TextureId::User(id);
+        let handle = renderer
+            .create_image(
+                UVec2::new(size.0 as u32, size.1 as u32),
+                self.smoothing_mode(texture_id, options),
+                pixels,
+            )
+            .map_err(|source| WindowWrapperError::TextureUpload { texture_id, source })?;
+        #[cfg(feature = "profiling")]
+        tracing::debug!(
+            ?texture_id,
+            width = size.0,
+            height = size.1,
+            "texture uploaded"
+        );
+        self.user_textures.insert(
+            id,
+            Texture {
+                handle,
+                size,
+                cpu_copy: None,
+                options,
+                name,
+            },
+        );
+        Ok(texture_id)
+    }
+
+    fn set_textures<R: Renderer<Image = I>>(
+        &mut self,
+        textures_delta: egui::TexturesDelta,
+        renderer: &mut R,
+        errors: &mut Vec<WindowWrapperError>,
+    ) {
+        profile_scope!("set_textures");
+        for (texture_id, image_delta) in textures_delta.set {
+            let id = match texture_id {
+                egui::TextureId::Managed(texture_id) => texture_id,
+                egui::TextureId::User(_) => continue,
+            };
+
+            let keep_cpu_copy = match self.cpu_copy_overrides.get(&id) {
+                Some(keep) => *keep,
+                None => match self.cpu_copy_policy {
+                    CpuCopyPolicy::FontAtlas => {
+                        matches!(image_delta.image, egui::ImageData::Font(_))
+                    }
+                    CpuCopyPolicy::All => true,
+                    CpuCopyPolicy::None => false,
+                },
+            };
+            let image = RgbaImage::from(image_delta.image);
+            let image = if let Some(pos) = image_delta.pos {
+                // renderers can't update part of an image: patch the CPU copy
+                // and upload it again
+                let Some(mut full) = self
+                    .id_and_textures
+                    .get_mut(&id)
+                    .and_then(|t| t.cpu_copy.take())
+                else {
+                    // egui keeps updating the font atlas, report it once
+                    if self.partial_update_textures.insert(id) {
+                        errors.push(WindowWrapperError::PartialUpdateWithoutCpuCopy(texture_id));
+                    }
+                    continue;
+                };
+                full.blit(&image, pos);
+                full
+            } else {
+                self.partial_update_textures.remove(&id);
+                image
+            };
+
+            let handle = match renderer.create_image(
+                UVec2::new(image.size.0 as u32, image.size.1 as u32),
+                self.smoothing_mode(texture_id, image_delta.options),
+                &image.pixels,
+            ) {
+                Ok(handle) => handle,
+                Err(source) => {
+                    errors.push(WindowWrapperError::TextureUpload { texture_id, source });
+                    // keep the patched copy so that later partial updates
+                    // still have something to apply to
+                    if let Some(texture) = self.id_and_textures.get_mut(&id) {
+                        if image_delta.pos.is_some() {
+                            texture.cpu_copy = Some(image);
+                        }
+                    }
+                    continue;
+                }
+            };
+            #[cfg(feature = "profiling")]
+            tracing::debug!(
+                ?texture_id,
+                width = image.size.0,
+                height = image.size.1,
+                partial = image_delta.pos.is_some(),
+                "texture uploaded"
+            );
+            self.id_and_textures.insert(
+                id,
+                Texture {
+                    handle,
+                    size: image.size,
+                    cpu_copy: keep_cpu_copy.then_some(image),
+                    options: image_delta.options,
+                    name: None,
+                },
+            );
+        }
+    }
+
+    /// Picks the smoothing mode of a texture, warning the first time it asks
+    /// for filters speedy2d can't mix, see [`smoothing_mode_from_egui`].
+    fn smoothing_mode(
+        &mut self,
+        texture_id: egui::TextureId,
+        options: egui::TextureOptions,
+    ) -> ImageSmoothingMode {
+        // streaming textures and game views are uploaded every frame
+        if options.magnification != options.minification
+            && self.mixed_filter_textures.insert(texture_id)
+        {
+            log::warn!(
+                "texture {texture_id:?}: speedy2d can't use different magnification ({:?}) and \
+                 minification ({:?}) filters, using {:?} for both",
+                options.magnification,
+                options.minification,
+                options.magnification
+            );
+        }
+        smoothing_mode_from_egui(options)
+    }
+
+    /// Returns the texture drawn in place of missing textures, creating it
+    /// the first time.
+    fn fallback_texture<R: Renderer<Image = I>>(
+        &mut self,
+        renderer: &mut R,
+    ) -> Result<I, WindowWrapperError> {
+        if let Some(handle) = &self.fallback_texture {
+            return Ok(handle.clone());
+        }
+        const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+        const BLACK: [u8; 4] = [0, 0, 0, 255];
+        let handle = renderer
+            .create_image(
+                UVec2::new(2, 2),
+                ImageSmoothingMode::NearestNeighbor,
+                &[MAGENTA, BLACK, BLACK, MAGENTA].concat(),
+            )
+            .map_err(WindowWrapperError::FallbackTexture)?;
+        self.fallback_texture = Some(handle.clone());
+        Ok(handle)
+    }
+
+    fn free_textures(&mut self) {
+        profile_scope!("free_textures");
+        for id in self.to_free_textures.drain(..) {
+            if self.id_and_textures.remove(&id).is_some() {
+                #[cfg(feature = "profiling")]
+                tracing::debug!(texture_id = ?egui::TextureId::Managed(id), "texture freed");
+            }
+            self.cpu_copy_overrides.remove(&id);
+            self.partial_update_textures.remove(&id);
+        }
+    }
+}
+
+/// Checks that `pixels` are the straight RGBA pixels of an image of `size`.
+pub(crate) fn check_pixels(size: [usize; 2], pixels: &[u8]) -> Result<(), WindowWrapperError> {
+    match rgba_len(size) == Some(pixels.len()) {
+        true => Ok(()),
+        false => Err(WindowWrapperError::TexturePixels {
+            size,
+            len: pixels.len(),
+        }),
+    }
+}
+
+/// Returns the length of the RGBA pixels of an image of `size`, or `None` if
+/// it doesn't fit in memory.
+pub(crate) fn rgba_len(size: [usize; 2]) -> Option<usize> {
+    size[0].checked_mul(size[1])?.checked_mul(4)
+}
+
+/// Which textures keep a copy of their pixels in CPU memory.
+///
+/// speedy2d can't update part of an image, so egui's partial texture updates
+/// are applied to the CPU copy, which is then uploaded again. Textures without
+/// a copy only use GPU memory, but partial updates to them are dropped and
+/// reported once per texture as
+/// [`WindowWrapperError::PartialUpdateWithoutCpuCopy`], until egui uploads the
+/// whole texture again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CpuCopyPolicy {
+    /// Only the font atlas keeps a copy, since egui updates it whenever new
+    /// glyphs are needed.
+    #[default]
+    FontAtlas,
+    /// Every texture keeps a copy.
+    All,
+    /// No texture keeps a copy.
+    ///
+    /// The glyphs egui adds to the font atlas after its first upload are then
+    /// missing, and text using them is drawn blank.
+    None,
+}
+
+/// Memory statistics about the textures managed by a [`Painter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureStats {
+    /// Number of textures alive.
+    pub count: usize,
+    /// Bytes of pixel data uploaded to the GPU.
+    pub gpu_bytes: usize,
+    /// Bytes of pixel data kept in CPU memory.
+    pub cpu_bytes: usize,
+}
+
+/// A texture uploaded to the renderer.
+struct Texture<I> {
+    handle: I,
+    size: (usize, usize),
+    cpu_copy: Option<RgbaImage>,
+    options: egui::TextureOptions,
+    /// Name given by the user, for user textures.
+    name: Option<String>,
+}
+
+/// A texture alive in a [`Painter`], as listed by [`Painter::textures`].
+pub(crate) struct TextureInfo<'a, I> {
+    pub id: egui::TextureId,
+    pub handle: &'a I,
+    pub size: (usize, usize),
+    pub name: Option<&'a str>,
+    pub options: egui::TextureOptions,
+    pub cpu_copy: bool,
+}
+
+/// A user texture waiting for a renderer to be uploaded with.
+struct PendingTexture {
+    name: String,
+    image: RgbaImage,
+    options: egui::TextureOptions,
+}
+
+struct RgbaImage {
+    size: (usize, usize),
+    pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Copies `other` into this image, with its top left corner at `pos`.
+    fn blit(&mut self, other: &RgbaImage, pos: [usize; 2]) {
+        let width = other.size.0.min(self.size.0.saturating_sub(pos[0]));
+        for y in 0..other.size.1.min(self.size.1.saturating_sub(pos[1])) {
+            let src = y * other.size.0 * 4;
+            let dst = ((pos[1] + y) * self.size.0 + pos[0]) * 4;
+            self.pixels[dst..dst + width * 4].copy_from_slice(&other.pixels[src..src + width * 4]);
+        }
+    }
+
+    fn from(image: egui::ImageData) -> Self {
+        Self {
+            size: {
+                let size = image.size();
+                (size[0], size[1])
+            },
+            pixels: match image {
+                // egui pixels are premultiplied, renderers expect straight alpha
+                egui::ImageData::Font(font_image) => {
+                    // the font atlas is white with coverage as alpha: keep the
+                    // white even where the coverage is 0, so that filtering
+                    // doesn't darken the edges of glyphs
+                    let mut pixels = vec![];
+                    for color in font_image.srgba_pixels(None) {
+                        pixels.extend_from_slice(&[255, 255, 255, color.a()]);
+                    }
+                    pixels
+                }
+                egui::ImageData::Color(color_image) => {
+                    let mut pixels = vec![];
+                    for color in &color_image.pixels {
+                        pixels.extend_from_slice(&unmultiply(*color));
+                    }
+                    pixels
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SoftwareRenderer;
+
+    /// Draws a triangle with the texture `texture_id`, and returns the number
+    /// of errors.
+    fn paint(
+        painter: &mut Painter<crate::SoftwareImage>,
+        renderer: &mut SoftwareRenderer,
+        texture_id: egui::TextureId,
+    ) -> usize {
+        let mut mesh = epaint::Mesh::with_texture(texture_id);
+        for pos in [egui::pos2(0., 0.), egui::pos2(8., 0.), egui::pos2(0., 8.)] {
+            mesh.colored_vertex(pos, egui::Color32::WHITE);
+        }
+        mesh.add_triangle(0, 1, 2);
+        let primitives = vec![egui::ClippedPrimitive {
+            clip_rect: egui::Rect::EVERYTHING,
+            primitive: epaint::Primitive::Mesh(mesh),
+        }];
+        painter
+            .paint_primitives(
+                Default::default(),
+                primitives,
+                Transform::IDENTITY,
+                renderer,
+            )
+            .len()
+    }
+
+    #[test]
+    fn reports_missing_textures_once() {
+        let mut renderer = SoftwareRenderer::new((8, 8));
+        let mut painter = Painter::default();
+        let texture_id = egui::TextureId::User(0);
+
+        assert_eq!(paint(&mut painter, &mut renderer, texture_id), 1);
+        assert_eq!(paint(&mut painter, &mut renderer, texture_id), 0);
+
+        let image = egui::ColorImage::new([1, 1], egui::Color32::WHITE);
+        let added = painter.add_user_texture(&mut renderer, image, Default::default());
+        assert_eq!(added.ok(), Some(texture_id));
+        assert_eq!(paint(&mut painter, &mut renderer, texture_id), 0);
+
+        // missing again once freed
+        painter.free_user_texture(0);
+        assert_eq!(paint(&mut painter, &mut renderer, texture_id), 1);
+    }
+
+    #[test]
+    fn reports_partial_updates_without_cpu_copy_once() {
+        let mut renderer = SoftwareRenderer::new((8, 8));
+        let mut painter = Painter::default();
+        painter.set_cpu_copy_policy(CpuCopyPolicy::None);
+        let texture_id = egui::TextureId::Managed(1);
+        let image = || egui::ColorImage::new([2, 2], egui::Color32::WHITE);
+        let mut set = |delta: egui::epaint::ImageDelta| {
+            let textures_delta = egui::TexturesDelta {
+                set: vec![(texture_id, delta)],
+                free: vec![],
+            };
+            painter
+                .paint_primitives(textures_delta, vec![], Transform::IDENTITY, &mut renderer)
+                .len()
+        };
+        let partial = || egui::epaint::ImageDelta::partial([0, 0], image(), Default::default());
+
+        assert_eq!(
+            set(egui::epaint::ImageDelta::full(image(), Default::default())),
+            0
+        );
+        assert_eq!(set(partial()), 1);
+        assert_eq!(set(partial()), 0);
+
+        // reported again once the whole texture is uploaded again
+        assert_eq!(
+            set(egui::epaint::ImageDelta::full(image(), Default::default())),
+            0
+        );
+        assert_eq!(set(partial()), 1);
+    }
+
+    #[test]
+    fn oversized_textures_are_rejected() {
+        assert!(check_pixels([2, 1], &[0; 8]).is_ok());
+        let error = check_pixels([usize::MAX, 2], &[0; 8]).unwrap_err();
+        assert!(matches!(
+            error,
+            WindowWrapperError::TexturePixels { len: 8, .. }
+        ));
+        assert!(error.to_string().contains("too large"));
+
+        let mut painter = Painter::<crate::SoftwareImage>::default();
+        let error =
+            painter.load_user_texture_rgba("huge", [1 << 62, 1], vec![], Default::default());
+        assert!(error.is_err());
+    }
+}
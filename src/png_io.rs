@@ -0,0 +1,50 @@
+//! Reading and writing RGBA images as PNG.
+
+use std::{io, path::Path};
+
+/// Encodes straight RGBA pixels as a PNG.
+pub(crate) fn encode(size: [u32; 2], pixels: &[u8]) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, size[0], size[1]);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(pixels))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(bytes)
+}
+
+/// Writes straight RGBA pixels to a PNG file.
+pub(crate) fn write(path: &Path, size: [u32; 2], pixels: &[u8]) -> io::Result<()> {
+    std::fs::write(path, encode(size, pixels)?)
+}
+
+/// Decodes an 8 bits RGB or RGBA PNG into straight RGBA pixels.
+pub(crate) fn decode(bytes: &[u8]) -> io::Result<([u32; 2], Vec<u8>)> {
+    let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let (info, mut reader) = decoder.read_info().map_err(invalid)?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer).map_err(invalid)?;
+    let pixels = match (info.color_type, info.bit_depth) {
+        (png::ColorType::RGBA, png::BitDepth::Eight) => buffer,
+        (png::ColorType::RGB, png::BitDepth::Eight) => buffer
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        (color_type, bit_depth) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported PNG format {color_type:?} {bit_depth:?}"),
+            ))
+        }
+    };
+    Ok(([info.width, info.height], pixels))
+}
+
+/// Reads an 8 bits RGB or RGBA PNG file into straight RGBA pixels.
+pub(crate) fn read(path: &Path) -> io::Result<([u32; 2], Vec<u8>)> {
+    decode(&std::fs::read(path)?)
+}
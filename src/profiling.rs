@@ -0,0 +1,19 @@
+//! Profiling scopes, compiled in with the `profiling` feature.
+//!
+//! Frames are left to the application to mark, with
+//! `puffin::GlobalProfiler::lock().new_frame()`, since it may draw several
+//! windows or do work outside of them in a frame.
+
+/// Opens a puffin scope and enters a tracing span named `$name`, both closed
+/// at the end of the enclosing block. Does nothing without the `profiling`
+/// feature.
+macro_rules! profile_scope {
+    ($name:literal) => {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!($name);
+        #[cfg(feature = "profiling")]
+        let _span = tracing::trace_span!($name).entered();
+    };
+}
+
+pub(crate) use profile_scope;
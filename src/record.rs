@@ -0,0 +1,288 @@
+//! Recording and replay of the egui input of a [`WindowWrapper`](crate::WindowWrapper).
+//!
+//! A recording is a JSON-lines file. The first line is a [`RecordingHeader`]
+//! with the egui and egui_speedy2d versions it was made with, and it can be
+//! replayed with any semver-compatible version of both. Every following
+//! line is a [`RecordedFrame`] holding the [`RawInput`] given to egui for one
+//! frame.
+//!
+//! ```no_run
+//! # struct MyWindowHandler;
+//! # impl egui_speedy2d::WindowHandler for MyWindowHandler {}
+//! use egui_speedy2d::{record::InputRecorder, WindowWrapper};
+//!
+//! let recorder = InputRecorder::create("session.jsonl").unwrap();
+//! let wrapper = WindowWrapper::new(MyWindowHandler).with_input_recorder(recorder);
+//! ```
+
+use egui::RawInput;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+/// The egui version this crate is built against, as the major and minor
+/// version of the egui dependency in `Cargo.toml`. Patch releases don't
+/// change the input egui takes.
+const EGUI_VERSION: &str = "0.27";
+
+/// The first line of a recording.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingHeader {
+    pub egui_version: String,
+    pub crate_version: String,
+}
+
+impl RecordingHeader {
+    /// The header of recordings made with this build.
+    pub fn current() -> Self {
+        Self {
+            egui_version: EGUI_VERSION.to_owned(),
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+        }
+    }
+
+    /// Returns whether a recording with this header can be replayed by a
+    /// build with the `other` header: both versions must be semver-compatible.
+    pub fn is_compatible_with(&self, other: &RecordingHeader) -> bool {
+        semver_compatible(&self.egui_version, &other.egui_version)
+            && semver_compatible(&self.crate_version, &other.crate_version)
+    }
+}
+
+/// Returns whether two versions have the same major version, or the same
+/// minor version for `0.x` versions. Missing parts count as 0.
+fn semver_compatible(a: &str, b: &str) -> bool {
+    let parse = |version: &str| -> Option<(u64, u64)> {
+        let mut parts = version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+        Some((major, minor))
+    };
+    match (parse(a), parse(b)) {
+        (Some((0, a_minor)), Some((0, b_minor))) => a_minor == b_minor,
+        (Some((a_major, _)), Some((b_major, _))) => a_major == b_major,
+        _ => a == b,
+    }
+}
+
+/// The input of a single frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// Index of the frame since the recording started.
+    pub frame: u64,
+    /// Seconds elapsed since the recording started.
+    pub timestamp: f64,
+    /// The input given to egui.
+    pub input: RawInput,
+}
+
+/// Writes the input of every frame to a recording.
+pub struct InputRecorder {
+    writer: Box<dyn Write>,
+    start: Instant,
+    frame: u64,
+}
+
+impl InputRecorder {
+    /// Creates a recording file at `path`, replacing any existing file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Starts a recording written to `writer`.
+    pub fn new(writer: impl Write + 'static) -> io::Result<Self> {
+        let mut recorder = Self {
+            writer: Box::new(writer),
+            start: Instant::now(),
+            frame: 0,
+        };
+        recorder.write_line(&RecordingHeader::current())?;
+        Ok(recorder)
+    }
+
+    /// Appends the input of a frame to the recording.
+    pub fn record(&mut self, input: &RawInput) -> io::Result<()> {
+        let frame = RecordedFrame {
+            frame: self.frame,
+            timestamp: self.start.elapsed().as_secs_f64(),
+            input: input.clone(),
+        };
+        self.frame += 1;
+        self.write_line(&frame)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Reads back the input of a recording, frame by frame.
+///
+/// ```
+/// use egui_speedy2d::record::{InputRecorder, InputReplay};
+///
+/// let path = std::env::temp_dir().join("egui_speedy2d_replay.jsonl");
+/// let mut recorder = InputRecorder::create(&path).unwrap();
+/// let mut input = egui::RawInput::default();
+/// input.events.push(egui::Event::Text("a".to_owned()));
+/// recorder.record(&input).unwrap();
+/// recorder.record(&egui::RawInput::default()).unwrap();
+/// drop(recorder);
+///
+/// let mut replay = InputReplay::open(&path).unwrap();
+/// assert_eq!(replay.next_input().unwrap().events, input.events);
+/// assert!(replay.next_input().unwrap().events.is_empty());
+/// assert!(replay.next_input().is_none());
+/// ```
+pub struct InputReplay {
+    header: RecordingHeader,
+    lines: Box<dyn Iterator<Item = io::Result<String>>>,
+}
+
+impl InputReplay {
+    /// Opens a recording file, failing if it was made with versions of egui
+    /// or egui_speedy2d that aren't semver-compatible with this build.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let replay = Self::open_any_version(path)?;
+        let current = RecordingHeader::current();
+        if !replay.header.is_compatible_with(&current) {
+            return Err(ReplayError::VersionMismatch {
+                recorded: replay.header,
+                current,
+            });
+        }
+        Ok(replay)
+    }
+
+    /// Opens a recording file, whatever the versions it was made with.
+    pub fn open_any_version(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Reads a recording from `reader`, whatever the versions it was made
+    /// with.
+    pub fn new(reader: impl BufRead + 'static) -> Result<Self, ReplayError> {
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err(ReplayError::MissingHeader),
+        };
+        Ok(Self {
+            header,
+            lines: Box::new(lines),
+        })
+    }
+
+    /// Returns the header of the recording.
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    /// Returns the next recorded frame, or `None` at the end of the recording.
+    ///
+    /// A malformed line ends the replay.
+    pub fn next_frame(&mut self) -> Option<RecordedFrame> {
+        let line = self.lines.next()?;
+        match line
+            .map_err(ReplayError::from)
+            .and_then(|line| Ok(serde_json::from_str(&line)?))
+        {
+            Ok(frame) => Some(frame),
+            Err(error) => {
+                log::error!("input replay stopped: {error}");
+                self.lines = Box::new(std::iter::empty());
+                None
+            }
+        }
+    }
+
+    /// Returns the input of the next recorded frame, or `None` at the end of
+    /// the recording.
+    pub fn next_input(&mut self) -> Option<RawInput> {
+        self.next_frame().map(|frame| frame.input)
+    }
+}
+
+/// Why a recording couldn't be replayed.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The recording is empty.
+    MissingHeader,
+    /// The recording was made with versions of egui or egui_speedy2d that
+    /// aren't semver-compatible with this build.
+    VersionMismatch {
+        recorded: RecordingHeader,
+        current: RecordingHeader,
+    },
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read recording: {error}"),
+            Self::Json(error) => write!(f, "malformed recording: {error}"),
+            Self::MissingHeader => write!(f, "recording is empty"),
+            Self::VersionMismatch { recorded, current } => write!(
+                f,
+                "recording made with egui {} and egui_speedy2d {}, but running egui {} and \
+                 egui_speedy2d {}",
+                recorded.egui_version,
+                recorded.crate_version,
+                current.egui_version,
+                current.crate_version
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_compared_semver_compatibly() {
+        let header = |egui_version: &str, crate_version: &str| RecordingHeader {
+            egui_version: egui_version.to_owned(),
+            crate_version: crate_version.to_owned(),
+        };
+        let current = header("0.27", "0.5.0");
+        // recordings made before only the minor egui version was recorded
+        assert!(header("0.27.2", "0.5.0").is_compatible_with(&current));
+        assert!(header("0.27", "0.5.3").is_compatible_with(&current));
+        assert!(!header("0.28", "0.5.0").is_compatible_with(&current));
+        assert!(!header("0.27", "0.6.0").is_compatible_with(&current));
+        assert!(header("1.2", "0.5.0").is_compatible_with(&header("1.4", "0.5.0")));
+        assert!(!header("1.2", "0.5.0").is_compatible_with(&header("2.0", "0.5.0")));
+    }
+}
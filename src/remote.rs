@@ -0,0 +1,562 @@
+//! A debug server to drive a running application from tests or QA tools.
+//!
+//! A [`RemoteServer`] listens on a localhost TCP port or a Unix socket. Each
+//! connection sends [`Command`]s as JSON, one per line, and gets one
+//! [`Response`] line back for each. Input commands become egui events of the
+//! next frame, and screenshots go through
+//! [`egui::ViewportCommand::Screenshot`] and come back as PNG data: the
+//! server never writes files.
+//!
+//! ```no_run
+//! # struct MyWindowHandler;
+//! # impl egui_speedy2d::WindowHandler for MyWindowHandler {}
+//! use egui_speedy2d::{remote::RemoteServer, WindowWrapper};
+//!
+//! let server = RemoteServer::bind_tcp(7070).unwrap();
+//! let wrapper = WindowWrapper::new(MyWindowHandler).with_remote_server(server);
+//! ```
+//!
+//! A test can then drive the application with a [`RemoteClient`], or by hand:
+//!
+//! ```text
+//! $ echo '{"command":"click","pos":{"x":40,"y":20}}' | nc localhost 7070
+//! {"response":"ok"}
+//! ```
+
+use base64::Engine;
+use egui::{Context, Event, LayerId, Modifiers, PointerButton, Pos2, RawInput, Rect};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// A request sent to a [`RemoteServer`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Command {
+    /// Moves the pointer, in points.
+    PointerMove { pos: Pos2 },
+    /// Moves the pointer, then presses and releases a button.
+    Click {
+        pos: Pos2,
+        #[serde(default = "primary")]
+        button: PointerButton,
+    },
+    /// Types text into the focused widget.
+    Text { text: String },
+    /// Presses and releases a key.
+    Key {
+        key: egui::Key,
+        #[serde(default)]
+        modifiers: Modifiers,
+    },
+    /// Captures the next frame, answered once it is drawn.
+    Screenshot,
+    /// Returns egui's memory, in its debug representation.
+    DumpMemory,
+    /// Returns the layers of the UI, back to front.
+    Layers,
+}
+
+fn primary() -> PointerButton {
+    PointerButton::Primary
+}
+
+/// The answer to a [`Command`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    /// The command was queued for the next frame.
+    Ok,
+    /// The frame captured by [`Command::Screenshot`].
+    Screenshot {
+        size: [usize; 2],
+        /// The frame encoded as PNG, in base64.
+        png: String,
+    },
+    Memory {
+        memory: String,
+    },
+    Layers {
+        layers: Vec<LayerInfo>,
+    },
+    /// The command was malformed or failed.
+    Error {
+        message: String,
+    },
+}
+
+/// A layer of the UI, as returned by [`Command::Layers`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LayerInfo {
+    pub layer: LayerId,
+    /// The area the layer covers, if it is an area such as a window.
+    pub rect: Option<Rect>,
+    pub visible: bool,
+}
+
+struct Request {
+    command: Command,
+    reply: Sender<Response>,
+}
+
+/// Listens for [`Command`]s on a local socket.
+///
+/// [`WindowWrapper::with_remote_server`](crate::WindowWrapper::with_remote_server)
+/// handles the commands at the start of every frame, and keeps the window
+/// redrawing while a client is connected so that they are not left waiting.
+/// Each connection is served by its own thread, which waits for the frame to
+/// answer.
+///
+/// Dropping the server stops listening. Connections already open are closed
+/// by their client.
+///
+/// ```
+/// use egui_speedy2d::remote::{RemoteClient, RemoteServer};
+///
+/// let mut server = RemoteServer::bind_tcp(0).unwrap();
+/// let port = server.local_addr().unwrap().port();
+/// let client = std::thread::spawn(move || {
+///     let mut client = RemoteClient::connect_tcp(port).unwrap();
+///     client.click(egui::pos2(10., 20.)).unwrap();
+///     client.layers().unwrap()
+/// });
+///
+/// // what `WindowWrapper` does every frame
+/// let ctx = egui::Context::default();
+/// let mut events = vec![];
+/// while !client.is_finished() {
+///     let mut raw_input = egui::RawInput::default();
+///     server.handle_commands(&ctx, &mut raw_input);
+///     events.extend(raw_input.events.iter().cloned());
+///     ctx.run(raw_input, |ctx| {
+///         egui::Window::new("Hello").show(ctx, |ui| ui.label("World"));
+///     });
+/// }
+///
+/// assert!(events.contains(&egui::Event::PointerMoved(egui::pos2(10., 20.))));
+/// let layers = client.join().unwrap();
+/// assert!(layers.iter().any(|layer| layer.layer.order == egui::Order::Middle));
+/// ```
+pub struct RemoteServer {
+    requests: Receiver<Request>,
+    local_addr: Option<SocketAddr>,
+    /// Screenshots waiting for the next frame.
+    screenshots: Vec<Sender<Response>>,
+    /// Connections being served.
+    clients: Arc<AtomicUsize>,
+    /// Tells the listener thread to stop.
+    shutdown: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+}
+
+/// How often the listener thread checks whether the server was dropped,
+/// while no connection comes in.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+impl RemoteServer {
+    /// Listens on the given TCP port of the loopback interface, or on any
+    /// free port if `port` is 0.
+    pub fn bind_tcp(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let local_addr = listener.local_addr()?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::listen(
+            move || listener.accept().map(|(stream, _)| stream),
+            Some(local_addr),
+        ))
+    }
+
+    /// Listens on a Unix socket created at `path`.
+    #[cfg(unix)]
+    pub fn bind_unix(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let listener = std::os::unix::net::UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::listen(
+            move || listener.accept().map(|(stream, _)| stream),
+            None,
+        ))
+    }
+
+    /// Serves the connections returned by `accept`, a non-blocking listener,
+    /// until the server is dropped.
+    fn listen<S: Stream>(
+        mut accept: impl FnMut() -> io::Result<S> + Send + 'static,
+        local_addr: Option<SocketAddr>,
+    ) -> Self {
+        let (sender, requests) = mpsc::channel();
+        let clients = Arc::new(AtomicUsize::new(0));
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let listener = {
+            let clients = clients.clone();
+            let shutdown = shutdown.clone();
+            thread::spawn(move || {
+                while !shutdown.load(Ordering::Relaxed) {
+                    match accept() {
+                        Ok(stream) => {
+                            // accepted streams may inherit the non-blocking mode
+                            if stream.set_nonblocking(false).is_ok() {
+                                spawn_connection(stream, sender.clone(), clients.clone());
+                            }
+                        }
+                        Err(_) => thread::sleep(ACCEPT_POLL_INTERVAL),
+                    }
+                }
+            })
+        };
+        Self {
+            requests,
+            local_addr,
+            screenshots: vec![],
+            clients,
+            shutdown,
+            listener: Some(listener),
+        }
+    }
+
+    /// Returns the address the server listens on, when it uses TCP.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the number of clients connected.
+    pub fn client_count(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    /// Handles the commands received since the last call, before the frame
+    /// `raw_input` is given to.
+    ///
+    /// Input commands add their events to `raw_input`. Screenshots are
+    /// answered once `raw_input` holds the [`egui::Event::Screenshot`] asked
+    /// for.
+    pub fn handle_commands(&mut self, ctx: &Context, raw_input: &mut RawInput) {
+        let screenshot = raw_input.events.iter().find_map(|event| match event {
+            Event::Screenshot { image, .. } => Some(image.clone()),
+            _ => None,
+        });
+        if let Some(image) = screenshot {
+            let pixels = image.pixels.iter().flat_map(|c| c.to_array());
+            let pixels = pixels.collect::<Vec<_>>();
+            let size = [image.size[0] as u32, image.size[1] as u32];
+            let response = match crate::png_io::encode(size, &pixels) {
+                Ok(png) => Response::Screenshot {
+                    size: image.size,
+                    png: base64::engine::general_purpose::STANDARD.encode(png),
+                },
+                Err(error) => Response::Error {
+                    message: format!("failed to encode the screenshot: {error}"),
+                },
+            };
+            for reply in self.screenshots.drain(..) {
+                let _ = reply.send(response.clone());
+            }
+        }
+
+        while let Ok(Request { command, reply }) = self.requests.try_recv() {
+            let events = &mut raw_input.events;
+            let response = match command {
+                Command::PointerMove { pos } => {
+                    events.push(Event::PointerMoved(pos));
+                    Response::Ok
+                }
+                Command::Click { pos, button } => {
+                    events.push(Event::PointerMoved(pos));
+                    for pressed in [true, false] {
+                        events.push(Event::PointerButton {
+                            pos,
+                            button,
+                            pressed,
+                            modifiers: raw_input.modifiers,
+                        });
+                    }
+                    Response::Ok
+                }
+                Command::Text { text } => {
+                    events.push(Event::Text(text));
+                    Response::Ok
+                }
+                Command::Key { key, modifiers } => {
+                    for pressed in [true, false] {
+                        events.push(Event::Key {
+                            key,
+                            physical_key: None,
+                            pressed,
+                            repeat: false,
+                            modifiers,
+                        });
+                    }
+                    Response::Ok
+                }
+                Command::Screenshot => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+                    self.screenshots.push(reply);
+                    continue;
+                }
+                Command::DumpMemory => Response::Memory {
+                    memory: ctx.memory(|memory| format!("{memory:#?}")),
+                },
+                Command::Layers => Response::Layers {
+                    layers: ctx.memory(|memory| {
+                        memory
+                            .layer_ids()
+                            .map(|layer| LayerInfo {
+                                layer,
+                                rect: memory.area_rect(layer.id),
+                                visible: memory.areas().is_visible(&layer),
+                            })
+                            .collect()
+                    }),
+                },
+            };
+            let _ = reply.send(response);
+        }
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+    }
+}
+
+/// A connection to a [`RemoteServer`].
+trait Stream: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl Stream for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        std::os::unix::net::UnixStream::try_clone(self)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// Counts a connection as a client for as long as it is served.
+struct Client(Arc<AtomicUsize>);
+
+impl Client {
+    fn new(clients: Arc<AtomicUsize>) -> Self {
+        clients.fetch_add(1, Ordering::Relaxed);
+        Self(clients)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Serves a connection on its own thread, until it is closed or the server
+/// is dropped.
+fn spawn_connection<S: Stream>(
+    mut writer: S,
+    requests: Sender<Request>,
+    clients: Arc<AtomicUsize>,
+) {
+    let Ok(reader) = writer.try_clone() else {
+        return;
+    };
+    let client = Client::new(clients);
+    thread::spawn(move || {
+        let _client = client;
+        for line in BufReader::new(reader).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str(&line) {
+                Ok(command) => {
+                    let (reply, response) = mpsc::channel();
+                    if requests.send(Request { command, reply }).is_err() {
+                        return;
+                    }
+                    match response.recv() {
+                        Ok(response) => response,
+                        Err(_) => return,
+                    }
+                }
+                Err(error) => Response::Error {
+                    message: format!("invalid command: {error}"),
+                },
+            };
+            if write_line(&mut writer, &response).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+fn write_line(writer: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// Sends [`Command`]s to a [`RemoteServer`] and waits for the answers.
+pub struct RemoteClient {
+    reader: BufReader<Box<dyn Read + Send>>,
+    writer: Box<dyn Write + Send>,
+}
+
+impl RemoteClient {
+    /// Connects to a server listening on the given TCP port of the loopback
+    /// interface.
+    pub fn connect_tcp(port: u16) -> io::Result<Self> {
+        let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+
+    /// Connects to a server listening on the Unix socket at `path`.
+    #[cfg(unix)]
+    pub fn connect_unix(path: impl AsRef<std::path::Path>) -> io::Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        Ok(Self::new(stream.try_clone()?, stream))
+    }
+
+    fn new(reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            reader: BufReader::new(Box::new(reader)),
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Sends a command and waits for its response.
+    pub fn send(&mut self, command: &Command) -> io::Result<Response> {
+        write_line(&mut self.writer, command)?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    /// Moves the pointer to `pos`, in points.
+    pub fn move_pointer(&mut self, pos: Pos2) -> io::Result<()> {
+        self.send_input(&Command::PointerMove { pos })
+    }
+
+    /// Clicks at `pos`, in points, with the primary button.
+    pub fn click(&mut self, pos: Pos2) -> io::Result<()> {
+        self.send_input(&Command::Click {
+            pos,
+            button: PointerButton::Primary,
+        })
+    }
+
+    /// Types text into the focused widget.
+    pub fn type_text(&mut self, text: impl Into<String>) -> io::Result<()> {
+        self.send_input(&Command::Text { text: text.into() })
+    }
+
+    /// Presses and releases a key.
+    pub fn press_key(&mut self, key: egui::Key, modifiers: Modifiers) -> io::Result<()> {
+        self.send_input(&Command::Key { key, modifiers })
+    }
+
+    /// Captures the next frame, and returns it encoded as PNG.
+    pub fn screenshot(&mut self) -> io::Result<Vec<u8>> {
+        match self.send(&Command::Screenshot)? {
+            Response::Screenshot { png, .. } => base64::engine::general_purpose::STANDARD
+                .decode(png)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns egui's memory, in its debug representation.
+    pub fn dump_memory(&mut self) -> io::Result<String> {
+        match self.send(&Command::DumpMemory)? {
+            Response::Memory { memory } => Ok(memory),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// Returns the layers of the UI, back to front.
+    pub fn layers(&mut self) -> io::Result<Vec<LayerInfo>> {
+        match self.send(&Command::Layers)? {
+            Response::Layers { layers } => Ok(layers),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    fn send_input(&mut self, command: &Command) -> io::Result<()> {
+        match self.send(command)? {
+            Response::Ok => Ok(()),
+            response => Err(unexpected(response)),
+        }
+    }
+}
+
+fn unexpected(response: Response) -> io::Error {
+    match response {
+        Response::Error { message } => io::Error::other(message),
+        response => io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected response {response:?}"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropping_the_server_stops_listening() {
+        let server = RemoteServer::bind_tcp(0).unwrap();
+        let port = server.local_addr().unwrap().port();
+
+        let client = RemoteClient::connect_tcp(port).unwrap();
+        let start = std::time::Instant::now();
+        while server.client_count() == 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "client not counted"
+            );
+            thread::sleep(ACCEPT_POLL_INTERVAL);
+        }
+        drop(client);
+        while server.client_count() > 0 {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "client still counted"
+            );
+            thread::sleep(ACCEPT_POLL_INTERVAL);
+        }
+
+        drop(server);
+        // the listener is closed once the server is dropped
+        TcpListener::bind((Ipv4Addr::LOCALHOST, port)).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! The drawing operations egui meshes need from a rendering backend, and the
+//! ones handlers draw the game with.
+
+use crate::SoftwareRenderer;
+use speedy2d::{
+    color::Color,
+    dimen::{UVec2, Vec2},
+    image::{ImageDataType, ImageHandle, ImageSmoothingMode},
+    shape::Rectangle,
+    Graphics2D,
+};
+
+/// A backend able to draw the meshes produced by egui.
+///
+/// [`Painter`](crate::Painter) does all the conversion work (texture
+/// management, colors, wrap modes, winding order), so a backend only needs
+/// to create images, clip and draw textured triangles.
+///
+/// It is implemented by speedy2d's [`Graphics2D`], and by
+/// [`SoftwareRenderer`](crate::SoftwareRenderer) to render without a GPU.
+pub trait Renderer {
+    /// A handle to an image created by this backend.
+    type Image: Clone;
+
+    /// Creates an image from straight (non-premultiplied) RGBA pixels.
+    fn create_image(
+        &mut self,
+        size: UVec2,
+        smoothing_mode: ImageSmoothingMode,
+        pixels: &[u8],
+    ) -> Result<Self::Image, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Restricts drawing to `rect`, or lifts the restriction with `None`.
+    fn set_clip(&mut self, rect: Option<Rectangle<i32>>);
+
+    /// Draws a textured triangle, tinted with a color per vertex.
+    ///
+    /// Vertices are given in clockwise order.
+    fn draw_triangle(
+        &mut self,
+        positions: [Vec2; 3],
+        colors: [Color; 3],
+        uvs: [Vec2; 3],
+        image: &Self::Image,
+    );
+}
+
+impl Renderer for Graphics2D {
+    type Image = ImageHandle;
+
+    fn create_image(
+        &mut self,
+        size: UVec2,
+        smoothing_mode: ImageSmoothingMode,
+        pixels: &[u8],
+    ) -> Result<Self::Image, Box<dyn std::error::Error + Send + Sync>> {
+        // speedy2d errors can't be sent between threads, keep their message
+        self.create_image_from_raw_pixels(ImageDataType::RGBA, smoothing_mode, size, pixels)
+            .map_err(|error| error.to_string().into())
+    }
+
+    fn set_clip(&mut self, rect: Option<Rectangle<i32>>) {
+        Graphics2D::set_clip(self, rect)
+    }
+
+    fn draw_triangle(
+        &mut self,
+        positions: [Vec2; 3],
+        colors: [Color; 3],
+        uvs: [Vec2; 3],
+        image: &Self::Image,
+    ) {
+        self.draw_triangle_image_tinted_three_color(positions, colors, uvs, image)
+    }
+}
+
+/// The surface a frame is drawn on, given to the drawing callbacks of a
+/// [`WindowHandler`](crate::WindowHandler).
+///
+/// It is implemented by speedy2d's [`Graphics2D`] and by
+/// [`SoftwareRenderer`], so that what is drawn through it also shows in a
+/// [`testing::Driver`](crate::testing::Driver). Anything else, such as text
+/// or images, is drawn on the [`Graphics2D`] of a window, returned by
+/// [`Canvas::graphics`].
+pub trait Canvas {
+    /// Fills the whole surface with `color`.
+    fn clear_screen(&mut self, color: Color);
+
+    /// Fills a rectangle, in pixels.
+    fn fill_rectangle(&mut self, rect: Rectangle, color: Color);
+
+    /// Fills a triangle, in pixels.
+    fn fill_triangle(&mut self, vertices: [Vec2; 3], color: Color);
+
+    /// Returns speedy2d's [`Graphics2D`] when drawing to a window, and `None`
+    /// otherwise.
+    fn graphics(&mut self) -> Option<&mut Graphics2D> {
+        None
+    }
+}
+
+impl Canvas for Graphics2D {
+    fn clear_screen(&mut self, color: Color) {
+        Graphics2D::clear_screen(self, color)
+    }
+
+    fn fill_rectangle(&mut self, rect: Rectangle, color: Color) {
+        self.draw_rectangle(rect, color)
+    }
+
+    fn fill_triangle(&mut self, vertices: [Vec2; 3], color: Color) {
+        Graphics2D::draw_triangle(self, vertices, color)
+    }
+
+    fn graphics(&mut self) -> Option<&mut Graphics2D> {
+        Some(self)
+    }
+}
+
+/// What a [`WindowWrapper`](crate::WindowWrapper) needs from a renderer
+/// besides drawing meshes, to run its frames on it.
+pub(crate) trait WindowRenderer: Renderer + Canvas {
+    /// Reads back what has been drawn, as an opaque image.
+    fn capture(&mut self) -> egui::ColorImage;
+}
+
+impl WindowRenderer for Graphics2D {
+    fn capture(&mut self) -> egui::ColorImage {
+        let bitmap = Graphics2D::capture(self, ImageDataType::RGBA);
+        let size = [bitmap.size().x as usize, bitmap.size().y as usize];
+        let pixels = bitmap
+            .data()
+            .chunks_exact(4)
+            .map(|p| egui::Color32::from_rgb(p[0], p[1], p[2]))
+            .collect();
+        egui::ColorImage { size, pixels }
+    }
+}
+
+impl WindowRenderer for SoftwareRenderer {
+    fn capture(&mut self) -> egui::ColorImage {
+        let size = [self.size().x as usize, self.size().y as usize];
+        let pixels = self
+            .pixels()
+            .chunks_exact(4)
+            .map(|p| egui::Color32::from_rgb(p[0], p[1], p[2]))
+            .collect();
+        egui::ColorImage { size, pixels }
+    }
+}
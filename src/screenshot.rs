@@ -0,0 +1,73 @@
+//! Screenshots written to PNG files, asked for through the egui context.
+
+use egui::{Context, Id};
+use std::path::PathBuf;
+
+/// Writes the window to PNG files, from any
+/// [`WindowHandler`](crate::WindowHandler) callback.
+///
+/// ```
+/// use egui_speedy2d::ContextScreenshotExt;
+///
+/// let ctx = egui::Context::default();
+/// ctx.save_screenshot("screenshot.png");
+/// ```
+pub trait ContextScreenshotExt {
+    /// Writes the next frame drawn to a PNG file at `path`, once the window
+    /// is fully drawn, the game included.
+    ///
+    /// A file that can't be written is reported as
+    /// [`WindowWrapperError::Screenshot`](crate::WindowWrapperError::Screenshot).
+    /// To get the pixels instead, send
+    /// [`egui::ViewportCommand::Screenshot`]: they are delivered as an
+    /// [`egui::Event::Screenshot`] on the next frame.
+    fn save_screenshot(&self, path: impl Into<PathBuf>);
+}
+
+impl ContextScreenshotExt for Context {
+    fn save_screenshot(&self, path: impl Into<PathBuf>) {
+        let path = path.into();
+        self.data_mut(|d| {
+            d.get_temp_mut_or_default::<Vec<PathBuf>>(paths_id())
+                .push(path)
+        });
+        self.request_repaint();
+    }
+}
+
+fn paths_id() -> Id {
+    Id::new("egui_speedy2d::screenshot")
+}
+
+/// Takes the paths of the screenshots asked for through `ctx`.
+pub(crate) fn take_paths(ctx: &Context) -> Vec<PathBuf> {
+    ctx.data_mut(|d| d.remove_temp::<Vec<PathBuf>>(paths_id()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{testing::Snapshot, SoftwareRenderer, WindowHandler, WindowWrapper};
+
+    struct Clear;
+
+    impl WindowHandler for Clear {}
+
+    #[test]
+    fn requested_screenshots_are_written() {
+        let path = std::env::temp_dir().join("egui_speedy2d_request_screenshot.png");
+        let _ = std::fs::remove_file(&path);
+        let mut window = WindowWrapper::headless(Clear);
+        window.request_screenshot(&path);
+
+        let mut renderer = SoftwareRenderer::new((4, 3));
+        renderer.clear(speedy2d::color::Color::RED);
+        let end = window.frame(&mut renderer, None);
+        assert!(end.errors.is_empty());
+
+        let snapshot = Snapshot::load_png(&path).unwrap();
+        assert_eq!(snapshot.size, [4, 3]);
+        assert_eq!(snapshot.pixel(3, 2), [255, 0, 0, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+}
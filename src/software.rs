@@ -0,0 +1,347 @@
+//! A CPU rasterizer, to render egui frames without a GPU.
+
+use crate::{Canvas, Renderer};
+use speedy2d::{
+    color::Color,
+    dimen::{UVec2, Vec2},
+    image::ImageSmoothingMode,
+    shape::Rectangle,
+};
+use std::rc::Rc;
+
+/// A [`Renderer`] drawing into an RGBA buffer in memory.
+///
+/// It blends like speedy2d does on the GPU, so its output is a faithful
+/// stand-in for what a [`WindowWrapper`](crate::WindowWrapper) shows on screen,
+/// for example to test UIs in CI. See [`Painter`](crate::Painter) for how to
+/// render an egui frame with it.
+pub struct SoftwareRenderer {
+    size: UVec2,
+    pixels: Vec<u8>,
+    clip: Option<Rectangle<i32>>,
+    /// A white pixel, drawn stretched by [`Canvas::fill_triangle`].
+    white: SoftwareImage,
+}
+
+/// An image created by a [`SoftwareRenderer`].
+#[derive(Clone, Debug)]
+pub struct SoftwareImage(Rc<SoftwareImageData>);
+
+#[derive(Debug)]
+struct SoftwareImageData {
+    size: UVec2,
+    smoothing_mode: ImageSmoothingMode,
+    pixels: Vec<u8>,
+}
+
+/// Images are equal when they are clones of each other.
+impl PartialEq for SoftwareImage {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SoftwareImage {}
+
+impl std::hash::Hash for SoftwareImage {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        Rc::as_ptr(&self.0).hash(state);
+    }
+}
+
+impl SoftwareImage {
+    /// Returns the size of the image in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.0.size
+    }
+}
+
+impl SoftwareRenderer {
+    /// Creates a renderer with a buffer of the given size, cleared to
+    /// transparent black.
+    pub fn new(size: impl Into<UVec2>) -> Self {
+        let size = size.into();
+        Self {
+            size,
+            pixels: vec![0; size.x as usize * size.y as usize * 4],
+            clip: None,
+            white: SoftwareImage(Rc::new(SoftwareImageData {
+                size: UVec2::new(1, 1),
+                smoothing_mode: ImageSmoothingMode::NearestNeighbor,
+                pixels: vec![255; 4],
+            })),
+        }
+    }
+
+    /// Returns the size of the buffer in pixels.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Returns the buffer as straight RGBA pixels, row by row from the top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Consumes the renderer and returns its buffer.
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /// Returns the RGBA value of a pixel.
+    ///
+    /// Panics if the pixel is outside the buffer.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.size.x && y < self.size.y, "pixel out of bounds");
+        let i = (y as usize * self.size.x as usize + x as usize) * 4;
+        self.pixels[i..i + 4].try_into().unwrap_or_default()
+    }
+
+    /// Fills the whole buffer with `color`, ignoring the clip rect.
+    pub fn clear(&mut self, color: Color) {
+        let color = to_u8(color);
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    /// Returns the area that can be drawn to, as `(min_x, min_y, max_x, max_y)`
+    /// with the max excluded.
+    fn drawable_area(&self) -> (i32, i32, i32, i32) {
+        let (width, height) = (self.size.x as i32, self.size.y as i32);
+        match &self.clip {
+            None => (0, 0, width, height),
+            Some(clip) => (
+                clip.top_left().x.clamp(0, width),
+                clip.top_left().y.clamp(0, height),
+                clip.bottom_right().x.clamp(0, width),
+                clip.bottom_right().y.clamp(0, height),
+            ),
+        }
+    }
+
+    fn blend(&mut self, x: i32, y: i32, src: [f32; 4]) {
+        let i = (y as usize * self.size.x as usize + x as usize) * 4;
+        let dst = &mut self.pixels[i..i + 4];
+        let alpha = src[3];
+        // same as speedy2d: (SRC_ALPHA, ONE_MINUS_SRC_ALPHA) for colors,
+        // (ONE, ONE_MINUS_SRC_ALPHA) for alpha
+        for c in 0..3 {
+            let value = src[c] * alpha + dst[c] as f32 / 255. * (1. - alpha);
+            dst[c] = (value * 255.).round().clamp(0., 255.) as u8;
+        }
+        let value = alpha + dst[3] as f32 / 255. * (1. - alpha);
+        dst[3] = (value * 255.).round().clamp(0., 255.) as u8;
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    type Image = SoftwareImage;
+
+    fn create_image(
+        &mut self,
+        size: UVec2,
+        smoothing_mode: ImageSmoothingMode,
+        pixels: &[u8],
+    ) -> Result<Self::Image, Box<dyn std::error::Error + Send + Sync>> {
+        let expected = size.x as usize * size.y as usize * 4;
+        if pixels.len() != expected {
+            return Err(format!(
+                "expected {expected} bytes for a {}x{} image, got {}",
+                size.x,
+                size.y,
+                pixels.len()
+            )
+            .into());
+        }
+        Ok(SoftwareImage(Rc::new(SoftwareImageData {
+            size,
+            smoothing_mode,
+            pixels: pixels.to_vec(),
+        })))
+    }
+
+    fn set_clip(&mut self, rect: Option<Rectangle<i32>>) {
+        self.clip = rect;
+    }
+
+    fn draw_triangle(
+        &mut self,
+        mut positions: [Vec2; 3],
+        mut colors: [Color; 3],
+        mut uvs: [Vec2; 3],
+        image: &Self::Image,
+    ) {
+        let mut area = edge(positions[0], positions[1], positions[2]);
+        if area == 0. || !area.is_finite() {
+            return;
+        }
+        if area < 0. {
+            positions.swap(1, 2);
+            colors.swap(1, 2);
+            uvs.swap(1, 2);
+            area = -area;
+        }
+
+        let (min_x, min_y, max_x, max_y) = self.drawable_area();
+        let left = positions.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let right = positions
+            .iter()
+            .map(|p| p.x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let top = positions.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let bottom = positions
+            .iter()
+            .map(|p| p.y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_x = min_x.max(left.floor() as i32);
+        let min_y = min_y.max(top.floor() as i32);
+        // the casts saturate, so huge coordinates must not overflow here
+        let max_x = max_x.min((right.ceil() as i32).saturating_add(1));
+        let max_y = max_y.min((bottom.ceil() as i32).saturating_add(1));
+
+        let edges = [
+            (positions[1], positions[2]),
+            (positions[2], positions[0]),
+            (positions[0], positions[1]),
+        ];
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let mut weights = [0.; 3];
+                let mut inside = true;
+                for (weight, (a, b)) in weights.iter_mut().zip(edges) {
+                    *weight = edge(a, b, p);
+                    // pixels on a shared edge belong to exactly one triangle
+                    inside &= *weight > 0. || (*weight == 0. && owns_edge(a, b));
+                }
+                if !inside {
+                    continue;
+                }
+                let weights = weights.map(|w| w / area);
+                let interpolate = |values: [f32; 3]| {
+                    values[0] * weights[0] + values[1] * weights[1] + values[2] * weights[2]
+                };
+                let uv = Vec2::new(
+                    interpolate(uvs.map(|uv| uv.x)),
+                    interpolate(uvs.map(|uv| uv.y)),
+                );
+                let texel = sample(image, uv);
+                let color = [
+                    interpolate(colors.map(|c| c.r())) * texel[0],
+                    interpolate(colors.map(|c| c.g())) * texel[1],
+                    interpolate(colors.map(|c| c.b())) * texel[2],
+                    interpolate(colors.map(|c| c.a())) * texel[3],
+                ];
+                self.blend(x, y, color);
+            }
+        }
+    }
+}
+
+impl Canvas for SoftwareRenderer {
+    fn clear_screen(&mut self, color: Color) {
+        self.clear(color)
+    }
+
+    fn fill_rectangle(&mut self, rect: Rectangle, color: Color) {
+        let (min, max) = (*rect.top_left(), *rect.bottom_right());
+        self.fill_triangle([min, Vec2::new(max.x, min.y), max], color);
+        self.fill_triangle([min, max, Vec2::new(min.x, max.y)], color);
+    }
+
+    fn fill_triangle(&mut self, vertices: [Vec2; 3], color: Color) {
+        let white = self.white.clone();
+        self.draw_triangle(vertices, [color; 3], [Vec2::ZERO; 3], &white)
+    }
+}
+
+/// Twice the signed area of the triangle `(a, b, p)`: positive when `p` is on
+/// the clockwise side of `a -> b` in screen coordinates.
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// Tie-breaking rule for pixels exactly on an edge. For any edge, exactly one
+/// of `a -> b` and `b -> a` owns it, so triangles sharing it don't both draw
+/// the pixel.
+fn owns_edge(a: Vec2, b: Vec2) -> bool {
+    b.y > a.y || (b.y == a.y && b.x > a.x)
+}
+
+/// Samples an image with clamp-to-edge addressing, as speedy2d does.
+fn sample(image: &SoftwareImage, uv: Vec2) -> [f32; 4] {
+    let image = &image.0;
+    let (width, height) = (image.size.x as i64, image.size.y as i64);
+    if width == 0 || height == 0 {
+        return [0.; 4];
+    }
+    let texel = |x: i64, y: i64| {
+        let i = (y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize * 4;
+        [0, 1, 2, 3].map(|c| image.pixels[i + c] as f32 / 255.)
+    };
+    let x = uv.x * width as f32;
+    let y = uv.y * height as f32;
+    match image.smoothing_mode {
+        ImageSmoothingMode::NearestNeighbor => texel(x.floor() as i64, y.floor() as i64),
+        ImageSmoothingMode::Linear => {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let (a, b) = (texel(x0, y0), texel(x0 + 1, y0));
+            let (c, d) = (texel(x0, y0 + 1), texel(x0 + 1, y0 + 1));
+            [0, 1, 2, 3].map(|i| {
+                let top = a[i] + (b[i] - a[i]) * fx;
+                let bottom = c[i] + (d[i] - c[i]) * fx;
+                top + (bottom - top) * fy
+            })
+        }
+    }
+}
+
+fn to_u8(color: Color) -> [u8; 4] {
+    [color.r(), color.g(), color.b(), color.a()].map(|c| (c * 255.).round().clamp(0., 255.) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white(renderer: &mut SoftwareRenderer) -> SoftwareImage {
+        renderer
+            .create_image(
+                UVec2::new(1, 1),
+                ImageSmoothingMode::NearestNeighbor,
+                &[255; 4],
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn far_off_screen_triangles_are_clipped() {
+        let mut renderer = SoftwareRenderer::new((8, 8));
+        let image = white(&mut renderer);
+        // far past the i32 range, but small enough for the area to be finite
+        let far = 1e12;
+        renderer.draw_triangle(
+            [Vec2::new(0., 0.), Vec2::new(far, 0.), Vec2::new(0., far)],
+            [Color::RED; 3],
+            [Vec2::ZERO; 3],
+            &image,
+        );
+        assert_eq!(renderer.pixel(7, 7), [255, 0, 0, 255]);
+
+        renderer.draw_triangle(
+            [
+                Vec2::new(far, far),
+                Vec2::new(far * 1.5, far),
+                Vec2::new(far, far * 1.5),
+            ],
+            [Color::BLUE; 3],
+            [Vec2::ZERO; 3],
+            &image,
+        );
+        assert_eq!(renderer.pixel(7, 7), [255, 0, 0, 255]);
+    }
+}
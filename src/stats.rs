@@ -0,0 +1,284 @@
+//! Frame timings and draw statistics, and an overlay showing them.
+
+use crate::TextureStats;
+use egui::{Context, Id};
+use std::{collections::VecDeque, fmt, time::Duration};
+
+/// Where the time of a frame went, and what was drawn.
+///
+/// [`WindowWrapper`](crate::WindowWrapper) measures every frame it draws, over
+/// its main context and the named ones added with
+/// [`WindowWrapper::with_context`](crate::WindowWrapper::with_context).
+/// Handlers can read the numbers of the previous frame with
+/// [`FrameStats::last`], for example to log them:
+///
+/// ```
+/// use egui_speedy2d::{FrameStats, TextureStats};
+/// use std::time::Duration;
+///
+/// let stats = FrameStats {
+///     frame_time: Duration::from_micros(16_700),
+///     on_draw: Duration::from_micros(2_000),
+///     triangles: 1200,
+///     meshes: 14,
+///     textures: TextureStats { count: 2, gpu_bytes: 4 << 20, cpu_bytes: 0 },
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     stats.to_string(),
+///     "16.7 ms/frame (on_draw 2.0 ms, end_frame 0.0 ms, tessellation 0.0 ms, draw 0.0 ms), \
+///      1200 triangles in 14 meshes, 2 textures using 4.0 MiB"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Time since the previous frame started.
+    pub frame_time: Duration,
+    /// Time spent in the handler's `on_draw`, building the UI.
+    pub on_draw: Duration,
+    /// Time spent in egui's `end_frame`, laying out the UI.
+    pub end_frame: Duration,
+    /// Time spent turning egui shapes into meshes.
+    pub tessellation: Duration,
+    /// Time spent uploading textures and drawing meshes with speedy2d.
+    pub draw: Duration,
+    /// Number of triangles drawn, before they are split to emulate texture
+    /// wrap modes.
+    pub triangles: usize,
+    /// Number of meshes drawn, each with a single texture and clip rect.
+    pub meshes: usize,
+    /// The textures alive once the frame was drawn.
+    pub textures: TextureStats,
+}
+
+const ID: &str = "egui_speedy2d::frame_stats";
+
+impl FrameStats {
+    /// Returns the statistics of the last frame drawn by the
+    /// [`WindowWrapper`](crate::WindowWrapper) of `ctx`, if any.
+    pub fn last(ctx: &Context) -> Option<Self> {
+        ctx.data(|d| d.get_temp(Id::new(ID)))
+    }
+
+    /// Clears the numbers summed over the contexts drawn in a frame.
+    pub(crate) fn clear_draw(&mut self) {
+        self.tessellation = Duration::ZERO;
+        self.draw = Duration::ZERO;
+        self.triangles = 0;
+        self.meshes = 0;
+        self.textures = TextureStats::default();
+    }
+
+    /// Adds the meshes and triangles of a tessellated frame.
+    pub(crate) fn count(&mut self, primitives: &[egui::ClippedPrimitive]) {
+        for primitive in primitives {
+            if let epaint::Primitive::Mesh(mesh) = &primitive.primitive {
+                self.meshes += 1;
+                self.triangles += mesh.indices.len() / 3;
+            }
+        }
+    }
+
+    /// Adds the textures of a painter.
+    pub(crate) fn add_textures(&mut self, textures: TextureStats) {
+        self.textures.count += textures.count;
+        self.textures.gpu_bytes += textures.gpu_bytes;
+        self.textures.cpu_bytes += textures.cpu_bytes;
+    }
+
+    /// Makes these the statistics returned by [`FrameStats::last`].
+    pub(crate) fn publish(&self, ctx: &Context) {
+        ctx.data_mut(|d| d.insert_temp(Id::new(ID), *self));
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.;
+        write!(
+            f,
+            "{:.1} ms/frame (on_draw {:.1} ms, end_frame {:.1} ms, tessellation {:.1} ms, \
+             draw {:.1} ms), {} triangles in {} meshes, {} textures using {:.1} MiB",
+            ms(self.frame_time),
+            ms(self.on_draw),
+            ms(self.end_frame),
+            ms(self.tessellation),
+            ms(self.draw),
+            self.triangles,
+            self.meshes,
+            self.textures.count,
+            (self.textures.gpu_bytes + self.textures.cpu_bytes) as f64 / (1 << 20) as f64,
+        )
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1e3)
+}
+
+/// Number of frames shown in the frame time graph.
+const HISTORY_LEN: usize = 240;
+
+/// An overlay showing the [`FrameStats`] of the last frames, toggled by a key.
+pub(crate) struct StatsOverlay {
+    key: egui::Key,
+    visible: bool,
+    history: VecDeque<FrameStats>,
+}
+
+impl StatsOverlay {
+    pub(crate) fn new(key: egui::Key) -> Self {
+        Self {
+            key,
+            visible: false,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+        }
+    }
+
+    /// Toggles the overlay when the key is pressed.
+    pub(crate) fn handle_events(&mut self, events: &[egui::Event]) {
+        for event in events {
+            if let egui::Event::Key {
+                key,
+                pressed: true,
+                repeat: false,
+                ..
+            } = event
+            {
+                if *key == self.key {
+                    self.visible = !self.visible;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn push(&mut self, stats: FrameStats) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(stats);
+    }
+
+    /// Shows the overlay in the top right corner, if it is visible.
+    pub(crate) fn show(&self, ctx: &Context) {
+        if !self.visible {
+            return;
+        }
+        let Some(last) = self.history.back() else {
+            return;
+        };
+        egui::Area::new(Id::new("egui_speedy2d::stats_overlay"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8., 8.))
+            .order(egui::Order::Debug)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.style_mut().wrap = Some(false);
+                    self.graph(ui);
+                    egui::Grid::new("stats").num_columns(2).show(ui, |ui| {
+                        let fps = 1. / last.frame_time.as_secs_f64().max(1e-6);
+                        ui.label("frame");
+                        ui.label(format!("{} ({fps:.0} fps)", millis(last.frame_time)));
+                        ui.end_row();
+                        for (name, duration) in [
+                            ("on_draw", last.on_draw),
+                            ("end_frame", last.end_frame),
+                            ("tessellation", last.tessellation),
+                            ("draw", last.draw),
+                        ] {
+                            ui.label(name);
+                            ui.label(millis(duration));
+                            ui.end_row();
+                        }
+                        ui.label("triangles");
+                        ui.label(format!("{} in {} meshes", last.triangles, last.meshes));
+                        ui.end_row();
+                        let mib = |bytes: usize| bytes as f64 / (1 << 20) as f64;
+                        ui.label("textures");
+                        ui.label(format!(
+                            "{} ({:.1} MiB GPU, {:.1} MiB CPU)",
+                            last.textures.count,
+                            mib(last.textures.gpu_bytes),
+                            mib(last.textures.cpu_bytes)
+                        ));
+                        ui.end_row();
+                    });
+                });
+            });
+    }
+
+    /// Draws the frame times, with lines at 60 and 30 fps.
+    fn graph(&self, ui: &mut egui::Ui) {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(240., 60.), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+        painter.rect_filled(rect, 0., visuals.extreme_bg_color);
+
+        // 40 ms at the top
+        let y = |duration: Duration| {
+            let t = (duration.as_secs_f32() / 0.040).min(1.);
+            rect.bottom() - t * rect.height()
+        };
+        for (fps, color) in [(60., egui::Color32::GREEN), (30., egui::Color32::RED)] {
+            let y = y(Duration::from_secs_f32(1. / fps));
+            painter.hline(
+                rect.x_range(),
+                y,
+                egui::Stroke::new(1., color.gamma_multiply(0.5)),
+            );
+        }
+
+        let dx = rect.width() / HISTORY_LEN as f32;
+        let start = rect.right() - dx * self.history.len() as f32;
+        let stroke = egui::Stroke::new(1., visuals.text_color());
+        for (i, stats) in self.history.iter().enumerate() {
+            let x = start + dx * i as f32;
+            painter.vline(x, y(stats.frame_time)..=rect.bottom(), stroke);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Canvas, ContextNamedExt, SoftwareImage, SoftwareRenderer, WindowHandler, WindowWrapper,
+    };
+    use speedy2d::window::WindowHelper;
+
+    /// Shows a label in the main context, and in the "hud" one if any.
+    struct Labels;
+
+    impl WindowHandler for Labels {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &Context,
+        ) {
+            let hud = egui_ctx.named_context("hud");
+            for ctx in std::iter::once(egui_ctx).chain(hud.as_ref()) {
+                egui::Area::new("label".into()).show(ctx, |ui| ui.label("Hello"));
+            }
+        }
+    }
+
+    /// Returns the statistics of the second frame, areas being hidden on the
+    /// first one while they are measured.
+    fn frame_stats(mut window: WindowWrapper<(), SoftwareImage>) -> FrameStats {
+        for _ in 0..2 {
+            window.frame(&mut SoftwareRenderer::new((100, 100)), None);
+        }
+        window.frame_stats()
+    }
+
+    #[test]
+    fn named_contexts_are_counted() {
+        let main = frame_stats(WindowWrapper::headless(Labels));
+        let both = frame_stats(WindowWrapper::headless(Labels).with_context("hud", 1));
+        assert!(main.meshes > 0);
+        assert_eq!(both.meshes, 2 * main.meshes);
+        assert_eq!(both.triangles, 2 * main.triangles);
+        assert_eq!(both.textures.count, 2 * main.textures.count);
+    }
+}
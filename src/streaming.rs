@@ -0,0 +1,216 @@
+//! Textures whose content changes every frame, such as video frames or live
+//! plots.
+
+use crate::{painter::check_pixels, Painter, Renderer, WindowWrapperError};
+use egui::{load::SizedTexture, mutex::Mutex, Context, Id};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Weak},
+};
+
+/// A fixed-size texture meant to be updated every frame.
+///
+/// Updates only write into the texture's pixel buffer. The texture is
+/// uploaded when it is drawn after an update, replacing its previous GPU
+/// image right away, so a streaming texture never holds more than one GPU
+/// image, however often it changes. Textures that are not drawn give back
+/// their GPU image once more than [`StreamingTexture::set_max_gpu_images`]
+/// exist, and are uploaded again when drawn.
+///
+/// The texture is freed when the last clone of it is dropped.
+///
+/// ```
+/// use egui_speedy2d::{Painter, SoftwareRenderer, StreamingTexture};
+///
+/// let ctx = egui::Context::default();
+/// let mut renderer = SoftwareRenderer::new((100, 100));
+/// let mut painter = Painter::default();
+/// let texture = StreamingTexture::new(&ctx, [2, 2], Default::default());
+///
+/// for color in [[255, 0, 0, 255], [0, 255, 0, 255]] {
+///     texture.update(&color.repeat(4)).unwrap();
+///     let full_output = ctx.run(Default::default(), |ctx| {
+///         egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
+///             ui.image((texture.id(), egui::vec2(20., 20.)));
+///         });
+///     });
+///     assert!(painter.paint(&ctx, full_output, &mut renderer).is_empty());
+///     assert_eq!(renderer.pixel(10, 10), color);
+/// }
+/// // the font atlas and a single image for the streaming texture
+/// assert_eq!(painter.texture_stats().count, 2);
+/// assert!(texture.update(&[0; 3]).is_err());
+///
+/// // past the limit, textures that are not drawn give back their image
+/// StreamingTexture::set_max_gpu_images(&ctx, Some(1));
+/// let other = StreamingTexture::new(&ctx, [2, 2], Default::default());
+/// let full_output = ctx.run(Default::default(), |ctx| {
+///     egui::CentralPanel::default().show(ctx, |ui| ui.image(other.sized()));
+/// });
+/// painter.paint(&ctx, full_output, &mut renderer);
+/// assert_eq!(painter.texture_stats().count, 2);
+/// ```
+#[derive(Clone)]
+pub struct StreamingTexture {
+    id: u64,
+    size: [usize; 2],
+    slot: Arc<Mutex<Slot>>,
+}
+
+struct Slot {
+    size: [usize; 2],
+    pixels: Vec<u8>,
+    options: egui::TextureOptions,
+    /// Whether `pixels` changed since they were uploaded.
+    dirty: bool,
+}
+
+/// The streaming textures of a context.
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    max_gpu_images: Option<usize>,
+    slots: HashMap<u64, Weak<Mutex<Slot>>>,
+    /// Textures holding a GPU image, with the frame they were last drawn.
+    on_gpu: HashMap<u64, u64>,
+}
+
+/// Streaming textures use user texture ids from this one on, above the ones
+/// handed out by [`Painter::add_user_texture`] and the
+/// [`CHOSEN_USER_TEXTURE_IDS`](crate::CHOSEN_USER_TEXTURE_IDS).
+const FIRST_ID: u64 = 1 << 62;
+
+fn registry(ctx: &Context) -> Arc<Mutex<Registry>> {
+    ctx.data_mut(|d| {
+        d.get_temp_mut_or_default::<Arc<Mutex<Registry>>>(Id::new("egui_speedy2d::streaming"))
+            .clone()
+    })
+}
+
+impl StreamingTexture {
+    /// Creates a texture of the given size, transparent until updated.
+    pub fn new(ctx: &Context, size: [usize; 2], options: egui::TextureOptions) -> Self {
+        let slot = Arc::new(Mutex::new(Slot {
+            size,
+            pixels: vec![0; size[0] * size[1] * 4],
+            options,
+            dirty: true,
+        }));
+        let registry = registry(ctx);
+        let mut registry = registry.lock();
+        let id = FIRST_ID + registry.next_id;
+        registry.next_id += 1;
+        registry.slots.insert(id, Arc::downgrade(&slot));
+        Self { id, size, slot }
+    }
+
+    /// Limits how many streaming textures of `ctx` hold a GPU image at once.
+    /// Textures drawn during the frame keep theirs even past the limit.
+    ///
+    /// Defaults to no limit.
+    pub fn set_max_gpu_images(ctx: &Context, max_gpu_images: Option<usize>) {
+        registry(ctx).lock().max_gpu_images = max_gpu_images;
+    }
+
+    /// Returns the id to paint the texture with.
+    pub fn id(&self) -> egui::TextureId {
+        egui::TextureId::User(self.id)
+    }
+
+    /// Returns the size of the texture in pixels.
+    pub fn size(&self) -> [usize; 2] {
+        self.size
+    }
+
+    /// Returns the texture with its size, for `ui.image`.
+    pub fn sized(&self) -> SizedTexture {
+        SizedTexture::new(self.id(), [self.size[0] as f32, self.size[1] as f32])
+    }
+
+    /// Replaces the content of the texture with straight (non-premultiplied)
+    /// RGBA pixels.
+    ///
+    /// Fails with [`WindowWrapperError::TexturePixels`], leaving the texture
+    /// unchanged, if `pixels` isn't `size[0] * size[1] * 4` bytes long.
+    pub fn update(&self, pixels: &[u8]) -> Result<(), WindowWrapperError> {
+        check_pixels(self.size, pixels)?;
+        self.update_with(|buffer| buffer.copy_from_slice(pixels));
+        Ok(())
+    }
+
+    /// Lets `write` change the straight RGBA pixels of the texture in place.
+    pub fn update_with(&self, write: impl FnOnce(&mut [u8])) {
+        let mut slot = self.slot.lock();
+        write(&mut slot.pixels);
+        slot.dirty = true;
+    }
+}
+
+/// Uploads the streaming textures drawn by `primitives` that changed, and
+/// frees the GPU images of dropped or evicted ones.
+pub(crate) fn upload<R: Renderer>(
+    ctx: &Context,
+    painter: &mut Painter<R::Image>,
+    renderer: &mut R,
+    primitives: &[egui::ClippedPrimitive],
+    errors: &mut Vec<WindowWrapperError>,
+) {
+    let registry = registry(ctx);
+    let mut registry = registry.lock();
+    let frame = ctx.frame_nr();
+
+    let dropped = registry
+        .slots
+        .iter()
+        .filter(|(_, slot)| slot.strong_count() == 0)
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    for id in dropped {
+        registry.slots.remove(&id);
+        registry.on_gpu.remove(&id);
+        painter.free_user_texture(id);
+    }
+
+    let drawn = primitives
+        .iter()
+        .filter_map(|primitive| match &primitive.primitive {
+            epaint::Primitive::Mesh(mesh) => match mesh.texture_id {
+                egui::TextureId::User(id) => Some(id),
+                egui::TextureId::Managed(_) => None,
+            },
+            epaint::Primitive::Callback(_) => None,
+        })
+        .collect::<HashSet<_>>();
+    for id in drawn {
+        let Some(slot) = registry.slots.get(&id).and_then(Weak::upgrade) else {
+            continue;
+        };
+        let mut slot = slot.lock();
+        if slot.dirty || !registry.on_gpu.contains_key(&id) {
+            let size = (slot.size[0], slot.size[1]);
+            if let Err(error) =
+                painter.upload_user_texture(renderer, id, None, size, &slot.pixels, slot.options)
+            {
+                errors.push(error);
+                continue;
+            }
+            slot.dirty = false;
+        }
+        registry.on_gpu.insert(id, frame);
+    }
+
+    if let Some(max_gpu_images) = registry.max_gpu_images {
+        let mut unused = registry
+            .on_gpu
+            .iter()
+            .filter(|(_, last_drawn)| **last_drawn < frame)
+            .map(|(id, last_drawn)| (*last_drawn, *id))
+            .collect::<Vec<_>>();
+        unused.sort();
+        let excess = registry.on_gpu.len().saturating_sub(max_gpu_images);
+        for (_, id) in unused.into_iter().take(excess) {
+            registry.on_gpu.remove(&id);
+            painter.free_user_texture(id);
+        }
+    }
+}
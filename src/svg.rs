@@ -0,0 +1,319 @@
+//! Export of egui frames as SVG, for bug reports and documentation.
+
+use crate::{png_io, Renderer};
+use base64::Engine;
+use speedy2d::{
+    color::Color,
+    dimen::{UVec2, Vec2},
+    image::ImageSmoothingMode,
+    shape::Rectangle,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A [`Renderer`] writing an SVG document.
+///
+/// Use it with a [`Painter`](crate::Painter), which keeps track of egui's
+/// textures and converts its meshes the same way as for the window. Meshes
+/// become triangles, clip rects become `clipPath`s, and textures are embedded
+/// as base64 PNG patterns.
+///
+/// SVG has no per-vertex colors, so each triangle is filled with the average
+/// of its vertex colors. Turning off egui's feathering with
+/// [`TessellationOptions`](egui::epaint::TessellationOptions) gives crisper
+/// results.
+///
+/// ```
+/// use egui_speedy2d::{Painter, SvgRenderer};
+///
+/// let ctx = egui::Context::default();
+/// ctx.tessellation_options_mut(|options| options.feathering = false);
+/// let full_output = ctx.run(Default::default(), |ctx| {
+///     egui::CentralPanel::default().show(ctx, |ui| ui.label("Hello World !"));
+/// });
+///
+/// let mut svg = SvgRenderer::new((640, 240));
+/// Painter::default().paint(&ctx, full_output, &mut svg);
+/// let svg = svg.finish();
+///
+/// let document = roxmltree::Document::parse(&svg).unwrap();
+/// let root = document.root_element();
+/// assert_eq!(root.tag_name().name(), "svg");
+/// assert_eq!(root.attribute("width"), Some("640"));
+/// let count = |name| document.descendants().filter(|n| n.has_tag_name(name)).count();
+/// assert!(count("polygon") > 0);
+/// assert!(count("clipPath") > 0);
+/// // the text is drawn from the font atlas
+/// assert!(count("pattern") > 0);
+/// let image = document.descendants().find(|n| n.has_tag_name("image")).unwrap();
+/// let href = image.attribute(("http://www.w3.org/1999/xlink", "href")).unwrap();
+/// assert!(href.starts_with("data:image/png;base64,"));
+/// ```
+pub struct SvgRenderer {
+    size: UVec2,
+    body: String,
+    clip_count: usize,
+    pattern_count: usize,
+    group_open: bool,
+    images: BTreeMap<u64, SvgImage>,
+}
+
+/// An image created by an [`SvgRenderer`].
+#[derive(Clone, Debug)]
+pub struct SvgImage(Rc<SvgImageData>);
+
+#[derive(Debug)]
+struct SvgImageData {
+    id: u64,
+    size: UVec2,
+    smoothing_mode: ImageSmoothingMode,
+    pixels: Vec<u8>,
+}
+
+impl SvgRenderer {
+    /// Creates a renderer for a document of the given size in pixels.
+    pub fn new(size: impl Into<UVec2>) -> Self {
+        Self {
+            size: size.into(),
+            body: String::new(),
+            clip_count: 0,
+            pattern_count: 0,
+            group_open: false,
+            images: Default::default(),
+        }
+    }
+
+    /// Finishes the document and returns it.
+    pub fn finish(mut self) -> String {
+        self.close_group();
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+            self.size.x, self.size.y
+        );
+        svg.push_str("<defs>\n");
+        for image in self.images.values() {
+            let image = &image.0;
+            let png = png_io::encode([image.size.x, image.size.y], &image.pixels)
+                .map(|png| base64::engine::general_purpose::STANDARD.encode(png))
+                .unwrap_or_default();
+            let rendering = match image.smoothing_mode {
+                ImageSmoothingMode::NearestNeighbor => "pixelated",
+                ImageSmoothingMode::Linear => "auto",
+            };
+            let _ = writeln!(
+                svg,
+                r#"<image id="texture{}" width="{}" height="{}" image-rendering="{rendering}" xlink:href="data:image/png;base64,{png}"/>"#,
+                image.id, image.size.x, image.size.y
+            );
+        }
+        svg.push_str("</defs>\n");
+        svg.push_str(&self.body);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn close_group(&mut self) {
+        if self.group_open {
+            self.body.push_str("</g>\n");
+            self.group_open = false;
+        }
+    }
+}
+
+impl Renderer for SvgRenderer {
+    type Image = SvgImage;
+
+    fn create_image(
+        &mut self,
+        size: UVec2,
+        smoothing_mode: ImageSmoothingMode,
+        pixels: &[u8],
+    ) -> Result<Self::Image, Box<dyn std::error::Error + Send + Sync>> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Ok(SvgImage(Rc::new(SvgImageData {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            size,
+            smoothing_mode,
+            pixels: pixels.to_vec(),
+        })))
+    }
+
+    fn set_clip(&mut self, rect: Option<Rectangle<i32>>) {
+        self.close_group();
+        if let Some(rect) = rect {
+            let id = self.clip_count;
+            self.clip_count += 1;
+            let _ = writeln!(
+                self.body,
+                r#"<clipPath id="clip{id}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                rect.top_left().x,
+                rect.top_left().y,
+                rect.width(),
+                rect.height()
+            );
+            let _ = writeln!(self.body, r#"<g clip-path="url(#clip{id})">"#);
+            self.group_open = true;
+        }
+    }
+
+    fn draw_triangle(
+        &mut self,
+        positions: [Vec2; 3],
+        colors: [Color; 3],
+        uvs: [Vec2; 3],
+        image: &Self::Image,
+    ) {
+        // average in premultiplied space, so that transparent vertices
+        // don't darken the color
+        let a = colors.iter().map(Color::a).sum::<f32>() / 3.;
+        let average = |channel: fn(&Color) -> f32| {
+            colors.iter().map(|c| channel(c) * c.a()).sum::<f32>() / (3. * a)
+        };
+        let (r, g, b) = (average(Color::r), average(Color::g), average(Color::b));
+        if a <= 0. {
+            return;
+        }
+        let points = positions
+            .iter()
+            .map(|p| format!("{},{}", p.x, p.y))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        // solid shapes sample a single texel, usually egui's white pixel
+        let data = &image.0;
+        let texel_space =
+            uvs.map(|uv| Vec2::new(uv.x * data.size.x as f32, uv.y * data.size.y as f32));
+        let Some(pattern_transform) = (uvs[0] != uvs[1] || uvs[0] != uvs[2])
+            .then(|| affine_mapping(texel_space, positions))
+            .flatten()
+        else {
+            let texel = texel(data, uvs[0]);
+            let _ = writeln!(
+                self.body,
+                r#"<polygon points="{points}" fill="{}" fill-opacity="{}"/>"#,
+                rgb([r * texel[0], g * texel[1], b * texel[2]]),
+                a * texel[3]
+            );
+            return;
+        };
+
+        self.images.entry(data.id).or_insert_with(|| image.clone());
+        let pattern = self.pattern_count;
+        self.pattern_count += 1;
+        let _ = writeln!(
+            self.body,
+            r##"<pattern id="pattern{pattern}" patternUnits="userSpaceOnUse" width="{}" height="{}" patternTransform="matrix({})"><use xlink:href="#texture{}"/></pattern>"##,
+            data.size.x,
+            data.size.y,
+            pattern_transform.map(|v| v.to_string()).join(" "),
+            data.id
+        );
+        if r >= 1. && g >= 1. && b >= 1. {
+            // untinted image
+            let _ = writeln!(
+                self.body,
+                r#"<polygon points="{points}" fill="url(#pattern{pattern})" fill-opacity="{a}"/>"#
+            );
+        } else {
+            // tinted, typically text from the white font atlas: use the
+            // texture as a mask over the tint color
+            let _ = writeln!(
+                self.body,
+                r#"<mask id="mask{pattern}"><polygon points="{points}" fill="url(#pattern{pattern})"/></mask>"#
+            );
+            let _ = writeln!(
+                self.body,
+                r#"<polygon points="{points}" fill="{}" fill-opacity="{a}" mask="url(#mask{pattern})"/>"#,
+                rgb([r, g, b])
+            );
+        }
+    }
+}
+
+/// Returns the SVG `matrix(a b c d e f)` mapping the `from` triangle onto the
+/// `to` triangle, or `None` if `from` is degenerate.
+fn affine_mapping(from: [Vec2; 3], to: [Vec2; 3]) -> Option<[f32; 6]> {
+    let (d1, d2) = (from[1] - from[0], from[2] - from[0]);
+    let (e1, e2) = (to[1] - to[0], to[2] - to[0]);
+    let det = d1.x * d2.y - d2.x * d1.y;
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    // [a c; b d] = [e1 e2] * inverse([d1 d2])
+    let a = (e1.x * d2.y - e2.x * d1.y) / det;
+    let c = (e2.x * d1.x - e1.x * d2.x) / det;
+    let b = (e1.y * d2.y - e2.y * d1.y) / det;
+    let d = (e2.y * d1.x - e1.y * d2.x) / det;
+    let e = to[0].x - a * from[0].x - c * from[0].y;
+    let f = to[0].y - b * from[0].x - d * from[0].y;
+    Some([a, b, c, d, e, f])
+}
+
+fn texel(image: &SvgImageData, uv: Vec2) -> [f32; 4] {
+    let (width, height) = (image.size.x as usize, image.size.y as usize);
+    if width == 0 || height == 0 {
+        return [0.; 4];
+    }
+    let x = ((uv.x * width as f32) as usize).min(width - 1);
+    let y = ((uv.y * height as f32) as usize).min(height - 1);
+    let i = (y * width + x) * 4;
+    [0, 1, 2, 3].map(|c| image.pixels.get(i + c).map_or(0., |v| *v as f32 / 255.))
+}
+
+fn rgb(color: [f32; 3]) -> String {
+    let [r, g, b] = color.map(|c| (c * 255.).round().clamp(0., 255.) as u8);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangles_and_clip_rects_become_svg_shapes() {
+        let mut svg = SvgRenderer::new((100, 50));
+        let white = svg
+            .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[255; 4])
+            .unwrap();
+        svg.set_clip(Some(Rectangle::new((10, 5).into(), (60, 45).into())));
+        let red = Color::from_rgba(1., 0., 0., 0.5);
+        svg.draw_triangle(
+            [(10., 5.).into(), (30., 5.).into(), (10., 25.).into()],
+            [red; 3],
+            [Vec2::ZERO; 3],
+            &white,
+        );
+        let svg = svg.finish();
+
+        let document = roxmltree::Document::parse(&svg).unwrap();
+        let element = |name| {
+            document
+                .descendants()
+                .find(|n| n.has_tag_name(name))
+                .unwrap()
+        };
+        let polygon = element("polygon");
+        assert_eq!(polygon.attribute("points"), Some("10,5 30,5 10,25"));
+        assert_eq!(polygon.attribute("fill"), Some("#ff0000"));
+        assert_eq!(polygon.attribute("fill-opacity"), Some("0.5"));
+
+        let clip = element("clipPath");
+        let rect = clip.first_element_child().unwrap();
+        let attributes = ["x", "y", "width", "height"].map(|name| rect.attribute(name));
+        assert_eq!(attributes, [Some("10"), Some("5"), Some("50"), Some("40")]);
+        let group = polygon.parent_element().unwrap();
+        let clip_id = clip.attribute("id").unwrap();
+        assert_eq!(
+            group.attribute("clip-path"),
+            Some(format!("url(#{clip_id})").as_str())
+        );
+        // solid shapes don't embed their texture
+        assert!(document.descendants().all(|n| !n.has_tag_name("image")));
+    }
+}
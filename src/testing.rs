@@ -0,0 +1,953 @@
+//! Helpers to test egui UIs without a window, with golden-image snapshots.
+//!
+//! A [`Harness`] runs a UI closure for a number of frames with scripted
+//! input, and renders the last one with a [`SoftwareRenderer`]. The resulting
+//! [`Snapshot`] can be compared to a reference PNG stored with the tests:
+//!
+//! ```no_run
+//! use egui_speedy2d::testing::{Harness, Tolerance};
+//!
+//! let mut harness = Harness::new((320, 240));
+//! harness.on_frame(1, [egui::Event::PointerMoved(egui::pos2(40., 40.))]);
+//! let snapshot = harness.run(3, |ctx| {
+//!     egui::CentralPanel::default().show(ctx, |ui| {
+//!         let _ = ui.button("Hover me");
+//!     });
+//! });
+//! snapshot
+//!     .assert_matches("tests/snapshots/button_hovered.png", Tolerance::default())
+//!     .unwrap();
+//! ```
+//!
+//! Set the `UPDATE_SNAPSHOTS` environment variable to write the reference
+//! images instead of comparing them, for example to create them the first
+//! time.
+//!
+//! A [`Driver`] runs a [`WindowWrapper`](crate::WindowWrapper) and its
+//! handler without a window and interacts with them, for end-to-end tests of
+//! an application. With the `accesskit` feature, it also finds widgets by
+//! label and role.
+
+#[cfg(feature = "accesskit")]
+use crate::ContextTransformExt;
+use crate::{
+    png_io, Canvas, NoHandler, SoftwareImage, SoftwareRenderer, WindowHandler, WindowWrapper,
+    WindowWrapperError,
+};
+use speedy2d::{
+    color::Color,
+    dimen::{UVec2, Vec2},
+    window::{MouseButton, VirtualKeyCode, WindowHelper},
+};
+#[cfg(feature = "accesskit")]
+use std::{cell::RefCell, rc::Rc};
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Runs egui UIs headless, for tests.
+///
+/// It runs the frames of a [`WindowWrapper::headless`] with the UI as its
+/// handler, so the UI is drawn just like in a window.
+///
+/// [`WindowWrapper::headless`]: crate::WindowWrapper::headless
+pub struct Harness {
+    window: WindowWrapper<(), SoftwareImage>,
+    size: UVec2,
+    clear_color: Color,
+    frame: usize,
+    scripted_events: HashMap<usize, Vec<egui::Event>>,
+    errors: Vec<WindowWrapperError>,
+    #[cfg(feature = "accesskit")]
+    tree_updates: Rc<RefCell<Option<egui::accesskit::TreeUpdate>>>,
+    #[cfg(feature = "accesskit")]
+    accesskit_tree: Option<egui::accesskit::TreeUpdate>,
+}
+
+impl Harness {
+    /// Creates a harness rendering to a screen of the given size in pixels.
+    pub fn new(size: impl Into<UVec2>) -> Self {
+        let window = WindowWrapper::headless(NoHandler);
+        #[cfg(feature = "accesskit")]
+        let tree_updates = Rc::new(RefCell::new(None));
+        #[cfg(feature = "accesskit")]
+        let window = {
+            let tree_updates = tree_updates.clone();
+            window.with_accesskit(move |update| *tree_updates.borrow_mut() = Some(update))
+        };
+        Self {
+            window,
+            size: size.into(),
+            clear_color: Color::TRANSPARENT,
+            frame: 0,
+            scripted_events: Default::default(),
+            errors: Default::default(),
+            #[cfg(feature = "accesskit")]
+            tree_updates,
+            #[cfg(feature = "accesskit")]
+            accesskit_tree: None,
+        }
+    }
+
+    /// Sets the color the screen is cleared to before each frame.
+    ///
+    /// Defaults to transparent.
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = color;
+        self
+    }
+
+    /// Returns the egui context, for example to change its style.
+    pub fn ctx(&self) -> &egui::Context {
+        &self.window.egui_ctx
+    }
+
+    /// Returns the number of frames run so far.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Schedules `events` to be fed to egui on the given frame, counted from
+    /// zero since the harness was created.
+    pub fn on_frame(
+        &mut self,
+        frame: usize,
+        events: impl IntoIterator<Item = egui::Event>,
+    ) -> &mut Self {
+        self.scripted_events
+            .entry(frame)
+            .or_default()
+            .extend(events);
+        self
+    }
+
+    /// Runs `frames` frames of `ui`, and returns a snapshot of the last one.
+    ///
+    /// Each frame is 1/60th of a second long. Every frame is rendered so that
+    /// textures stay up to date, which also means drawing problems of every
+    /// frame are collected, see [`Harness::errors`].
+    ///
+    /// Nothing is drawn under the UI, so a game view captured from the window
+    /// shows the clear color: use
+    /// [`GameViewSource::Pixels`](crate::GameViewSource::Pixels) instead.
+    ///
+    /// ```
+    /// use egui_speedy2d::testing::Harness;
+    ///
+    /// let ui = |ctx: &egui::Context| {
+    ///     egui::CentralPanel::default().show(ctx, |ui| {
+    ///         let _ = ui.button("Hover me");
+    ///     });
+    /// };
+    /// let idle = Harness::new((200, 100)).run(2, ui);
+    ///
+    /// let mut harness = Harness::new((200, 100));
+    /// harness.on_frame(0, [egui::Event::PointerMoved(egui::pos2(20., 15.))]);
+    /// let hovered = harness.run(2, ui);
+    ///
+    /// assert!(harness.errors().is_empty());
+    /// assert_ne!(idle, hovered);
+    /// ```
+    pub fn run(&mut self, frames: usize, mut ui: impl FnMut(&egui::Context)) -> Snapshot {
+        let mut renderer = SoftwareRenderer::new(self.size);
+        for _ in 0..frames {
+            self.window.input_resize(self.size);
+            let raw_input = &mut self.window.raw_input;
+            raw_input.time = Some(self.frame as f64 / 60.);
+            raw_input.predicted_dt = 1. / 60.;
+            let events = self.scripted_events.remove(&self.frame);
+            raw_input.events.extend(events.unwrap_or_default());
+
+            renderer = SoftwareRenderer::new(self.size);
+            renderer.clear(self.clear_color);
+            let end = self
+                .window
+                .frame_with(&mut UiHandler(&mut ui), &mut renderer, None);
+            self.errors.extend(end.errors);
+            #[cfg(feature = "accesskit")]
+            if let Some(update) = self.tree_updates.borrow_mut().take() {
+                self.accesskit_tree = Some(update);
+            }
+            self.frame += 1;
+        }
+        Snapshot {
+            size: [renderer.size().x, renderer.size().y],
+            pixels: renderer.into_pixels(),
+        }
+    }
+
+    /// Returns the accessibility tree of the last frame.
+    #[cfg(feature = "accesskit")]
+    pub fn accesskit_tree(&self) -> Option<&egui::accesskit::TreeUpdate> {
+        self.accesskit_tree.as_ref()
+    }
+
+    /// Returns the drawing problems encountered so far.
+    pub fn errors(&self) -> &[WindowWrapperError] {
+        &self.errors
+    }
+}
+
+/// Runs a UI closure as the handler of a frame.
+struct UiHandler<F>(F);
+
+impl<F: FnMut(&egui::Context)> WindowHandler for UiHandler<F> {
+    fn on_draw(
+        &mut self,
+        _: Option<&mut WindowHelper>,
+        _: &mut dyn Canvas,
+        egui_ctx: &egui::Context,
+    ) {
+        (self.0)(egui_ctx)
+    }
+}
+
+/// Drives a [`WindowWrapper`] and its [`WindowHandler`] without a window,
+/// the way a user would, for end-to-end tests.
+///
+/// Interactions go through the input handling of the wrapper, as if they came
+/// from the window: clicks move the mouse and press and release the left
+/// button, and keys are speedy2d key codes. Each interaction then runs a
+/// couple of frames so that the UI shows its effects. With the `accesskit`
+/// feature, widgets can also be found by label and role in the accessibility
+/// tree egui builds each frame, and clicked or typed into.
+///
+/// The wrapper is created with [`WindowWrapper::headless`] and draws with a
+/// [`SoftwareRenderer`]. Its drawing callbacks are run with no `WindowHelper`,
+/// since speedy2d can't create one without a window:
+/// [`WindowHandler::on_draw_before_ui`], [`WindowHandler::on_draw`] and
+/// [`WindowHandler::on_draw_after_ui`]. The lifecycle and input callbacks and
+/// [`WindowHandler::on_user_event`] are never run, and the drawing problems
+/// are returned by [`Driver::errors`] instead of going to
+/// [`WindowHandler::on_error`].
+///
+/// ```
+/// use egui_speedy2d::{testing::Driver, Canvas, WindowHandler, WindowWrapper};
+/// use speedy2d::{color::Color, window::{VirtualKeyCode, WindowHelper}};
+///
+/// #[derive(Default)]
+/// struct App {
+///     paused: bool,
+/// }
+///
+/// impl WindowHandler for App {
+///     fn on_draw_before_ui(
+///         &mut self,
+///         _: Option<&mut WindowHelper>,
+///         canvas: &mut dyn Canvas,
+///         _: &egui::Context,
+///     ) {
+///         canvas.clear_screen(Color::BLUE);
+///     }
+///
+///     fn on_draw(&mut self, _: Option<&mut WindowHelper>, _: &mut dyn Canvas, egui_ctx: &egui::Context) {
+///         if egui_ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+///             self.paused = !self.paused;
+///         }
+///         if self.paused {
+///             egui::Window::new("Paused").show(egui_ctx, |ui| ui.label("Press Escape"));
+///         }
+///     }
+/// }
+///
+/// let mut driver = Driver::new(WindowWrapper::headless(App::default()), (300, 200));
+/// let playing = driver.snapshot().clone();
+/// assert_eq!(playing.pixel(150, 100), [0, 0, 255, 255]);
+///
+/// let paused = driver.press_key(VirtualKeyCode::Escape);
+/// assert_ne!(&playing, paused);
+/// ```
+///
+/// With `accesskit`, widgets are found the way a screen reader sees them:
+///
+/// ```
+/// # #[cfg(feature = "accesskit")]
+/// # {
+/// use egui::accesskit::Role;
+/// use egui_speedy2d::{testing::{By, Driver}, Canvas, WindowHandler, WindowWrapper};
+/// use speedy2d::window::WindowHelper;
+///
+/// #[derive(Default)]
+/// struct App {
+///     count: usize,
+///     name: String,
+/// }
+///
+/// impl WindowHandler for App {
+///     fn on_draw(&mut self, _: Option<&mut WindowHelper>, _: &mut dyn Canvas, egui_ctx: &egui::Context) {
+///         egui::CentralPanel::default().show(egui_ctx, |ui| {
+///             if ui.button("Add").clicked() {
+///                 self.count += 1;
+///             }
+///             ui.label(format!("Count: {}", self.count));
+///             let label = ui.label("Name");
+///             ui.text_edit_singleline(&mut self.name).labelled_by(label.id);
+///         });
+///     }
+/// }
+///
+/// let mut driver = Driver::new(WindowWrapper::headless(App::default()), (300, 200));
+///
+/// driver.click(&By::label("Add").with_role(Role::Button)).unwrap();
+/// assert!(driver.find(&By::label("Count: 1")).is_ok());
+///
+/// let name = By::label("Name").with_role(Role::TextInput);
+/// driver.type_text(&name, "Ferris").unwrap();
+/// assert_eq!(driver.find(&name).unwrap().value.as_deref(), Some("Ferris"));
+///
+/// assert!(driver.click(&By::label("Remove")).is_err());
+/// # }
+/// ```
+///
+/// [`WindowWrapper`]: crate::WindowWrapper
+/// [`WindowWrapper::headless`]: crate::WindowWrapper::headless
+/// [`WindowHandler`]: crate::WindowHandler
+/// [`WindowHandler::on_draw_before_ui`]: crate::WindowHandler::on_draw_before_ui
+/// [`WindowHandler::on_draw`]: crate::WindowHandler::on_draw
+/// [`WindowHandler::on_draw_after_ui`]: crate::WindowHandler::on_draw_after_ui
+/// [`WindowHandler::on_user_event`]: crate::WindowHandler::on_user_event
+/// [`WindowHandler::on_error`]: crate::WindowHandler::on_error
+pub struct Driver<UserEventType = ()> {
+    window: WindowWrapper<UserEventType, SoftwareImage>,
+    size: UVec2,
+    #[cfg(feature = "accesskit")]
+    tree: Rc<RefCell<Option<egui::accesskit::TreeUpdate>>>,
+    snapshot: Snapshot,
+    errors: Vec<WindowWrapperError>,
+}
+
+/// Frames run after each interaction: one for egui to handle the events, and
+/// one for the UI to show their effects.
+const SETTLE_FRAMES: usize = 2;
+
+impl<UserEventType: 'static> Driver<UserEventType> {
+    /// Creates a driver running `window` on a screen of the given size in
+    /// pixels, and runs the first frames so that the UI is shown.
+    ///
+    /// With the `accesskit` feature, the accessibility tree of the window is
+    /// turned on and taken by the driver.
+    pub fn new(
+        window: WindowWrapper<UserEventType, SoftwareImage>,
+        size: impl Into<UVec2>,
+    ) -> Self {
+        #[cfg(feature = "accesskit")]
+        let tree = Rc::new(RefCell::new(None));
+        #[cfg(feature = "accesskit")]
+        let window = {
+            let tree = tree.clone();
+            window.with_accesskit(move |update| *tree.borrow_mut() = Some(update))
+        };
+        let size = size.into();
+        let mut window = window;
+        window.input_resize(size);
+        let mut driver = Self {
+            window,
+            size,
+            #[cfg(feature = "accesskit")]
+            tree,
+            snapshot: Snapshot {
+                size: [0, 0],
+                pixels: vec![],
+            },
+            errors: vec![],
+        };
+        driver.run(SETTLE_FRAMES);
+        driver
+    }
+
+    /// Returns the window being driven.
+    pub fn window(&self) -> &WindowWrapper<UserEventType, SoftwareImage> {
+        &self.window
+    }
+
+    /// Returns the rendering of the last frame.
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.snapshot
+    }
+
+    /// Returns the drawing problems encountered so far.
+    pub fn errors(&self) -> &[WindowWrapperError] {
+        &self.errors
+    }
+
+    /// Runs `frames` frames with no input, for example to let animations end.
+    pub fn run(&mut self, frames: usize) -> &Snapshot {
+        for _ in 0..frames {
+            let mut renderer = SoftwareRenderer::new(self.size);
+            let end = self.window.frame(&mut renderer, None);
+            self.errors.extend(end.errors);
+            self.snapshot = Snapshot {
+                size: [self.size.x, self.size.y],
+                pixels: renderer.into_pixels(),
+            };
+        }
+        &self.snapshot
+    }
+
+    /// Feeds `events` to egui on the next frame as they are, and runs the UI
+    /// until it shows their effects.
+    pub fn send(&mut self, events: impl IntoIterator<Item = egui::Event>) -> &Snapshot {
+        self.window.raw_input.events.extend(events);
+        self.run(SETTLE_FRAMES)
+    }
+
+    /// Clicks with the left button at `pos`, in window pixels.
+    ///
+    /// The position goes through the inverse of the paint transform of the
+    /// main context, as a click in the window would.
+    pub fn click_at(&mut self, pos: egui::Pos2) -> &Snapshot {
+        // hover first, as a real mouse would, so that the contexts know
+        // which one is under the pointer when the button is pressed
+        self.window.input_mouse_move(Vec2::new(pos.x, pos.y));
+        self.run(1);
+        self.window.input_mouse_button(MouseButton::Left, true);
+        self.window.input_mouse_button(MouseButton::Left, false);
+        self.run(SETTLE_FRAMES)
+    }
+
+    /// Presses and releases `key`.
+    pub fn press_key(&mut self, key: VirtualKeyCode) -> &Snapshot {
+        self.window.input_key(Some(key), true);
+        self.window.input_key(Some(key), false);
+        self.run(SETTLE_FRAMES)
+    }
+}
+
+#[cfg(feature = "accesskit")]
+impl<UserEventType: 'static> Driver<UserEventType> {
+    /// Returns every widget of the last frame matching `by`, in tree order.
+    pub fn find_all(&self, by: &By) -> Vec<Widget> {
+        let tree = self.tree.borrow();
+        let Some(tree) = tree.as_ref() else {
+            return vec![];
+        };
+        let names = tree
+            .nodes
+            .iter()
+            .filter_map(|(id, node)| Some((*id, node.name()?)))
+            .collect::<HashMap<_, _>>();
+        tree.nodes
+            .iter()
+            .map(|(id, node)| Widget {
+                id: *id,
+                role: node.role(),
+                label: node
+                    .name()
+                    .or_else(|| {
+                        node.labelled_by()
+                            .iter()
+                            .find_map(|id| names.get(id).copied())
+                    })
+                    .map(str::to_owned),
+                value: node.value().map(str::to_owned),
+                numeric_value: node.numeric_value(),
+                checked: node.checked(),
+                rect: node.bounds().map(|bounds| {
+                    egui::Rect::from_min_max(
+                        egui::pos2(bounds.x0 as f32, bounds.y0 as f32),
+                        egui::pos2(bounds.x1 as f32, bounds.y1 as f32),
+                    )
+                }),
+            })
+            .filter(|widget| by.matches(widget))
+            .collect()
+    }
+
+    /// Returns the only widget of the last frame matching `by`.
+    pub fn find(&self, by: &By) -> Result<Widget, DriverError> {
+        let mut widgets = self.find_all(by);
+        match widgets.len() {
+            0 => Err(DriverError::NotFound(by.clone())),
+            1 => Ok(widgets.remove(0)),
+            count => Err(DriverError::Ambiguous {
+                by: by.clone(),
+                count,
+            }),
+        }
+    }
+
+    /// Clicks the middle of the widget matching `by` with the left button.
+    pub fn click(&mut self, by: &By) -> Result<&Snapshot, DriverError> {
+        let widget = self.find(by)?;
+        let center = widget
+            .rect
+            .ok_or_else(|| DriverError::NoBounds(by.clone()))?
+            .center();
+        // the tree is in the space of the context, the mouse moves on screen
+        let pos = self.window.egui_ctx.paint_transform().apply(center);
+        Ok(self.click_at(pos))
+    }
+
+    /// Focuses the widget matching `by` and types `text` into it.
+    pub fn type_text(&mut self, by: &By, text: &str) -> Result<&Snapshot, DriverError> {
+        let widget = self.find(by)?;
+        self.window
+            .accesskit_actions()
+            .push(egui::accesskit::ActionRequest {
+                action: egui::accesskit::Action::Focus,
+                target: widget.id,
+                data: None,
+            });
+        self.run(SETTLE_FRAMES);
+        for c in text.chars() {
+            self.window.input_char(c);
+        }
+        Ok(self.run(SETTLE_FRAMES))
+    }
+}
+
+/// What a [`Driver`] looks for: widgets with the given label and role.
+///
+/// The label of a widget is its own text, or the text of the widget it is
+/// labelled by.
+#[cfg(feature = "accesskit")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct By {
+    label: Option<String>,
+    role: Option<egui::accesskit::Role>,
+}
+
+#[cfg(feature = "accesskit")]
+impl By {
+    /// Widgets labelled exactly `label`.
+    pub fn label(label: impl Into<String>) -> Self {
+        Self::default().with_label(label)
+    }
+
+    /// Widgets with the given role.
+    pub fn role(role: egui::accesskit::Role) -> Self {
+        Self::default().with_role(role)
+    }
+
+    /// Also requires the widgets to be labelled exactly `label`.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Also requires the widgets to have the given role.
+    pub fn with_role(mut self, role: egui::accesskit::Role) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    fn matches(&self, widget: &Widget) -> bool {
+        self.label
+            .as_ref()
+            .is_none_or(|label| widget.label.as_ref() == Some(label))
+            && self.role.is_none_or(|role| widget.role == role)
+    }
+}
+
+#[cfg(feature = "accesskit")]
+impl fmt::Display for By {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.label, self.role) {
+            (Some(label), Some(role)) => write!(f, "{role:?} labelled {label:?}"),
+            (Some(label), None) => write!(f, "widget labelled {label:?}"),
+            (None, Some(role)) => write!(f, "{role:?}"),
+            (None, None) => write!(f, "any widget"),
+        }
+    }
+}
+
+/// A widget found by a [`Driver`], as of the last frame.
+#[cfg(feature = "accesskit")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Widget {
+    pub id: egui::accesskit::NodeId,
+    pub role: egui::accesskit::Role,
+    pub label: Option<String>,
+    /// The text of text inputs.
+    pub value: Option<String>,
+    /// The value of sliders, drag values and progress bars.
+    pub numeric_value: Option<f64>,
+    /// The state of checkboxes, radio buttons and selectable labels.
+    pub checked: Option<egui::accesskit::Checked>,
+    /// Where the widget is on screen, in points.
+    pub rect: Option<egui::Rect>,
+}
+
+/// Why a [`Driver`] couldn't interact with a widget.
+#[cfg(feature = "accesskit")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DriverError {
+    /// No widget matches.
+    NotFound(By),
+    /// Several widgets match.
+    Ambiguous { by: By, count: usize },
+    /// The widget has no position on screen.
+    NoBounds(By),
+}
+
+#[cfg(feature = "accesskit")]
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(by) => write!(f, "no {by} found"),
+            Self::Ambiguous { by, count } => write!(f, "{count} widgets match {by}"),
+            Self::NoBounds(by) => write!(f, "{by} has no position on screen"),
+        }
+    }
+}
+
+#[cfg(feature = "accesskit")]
+impl std::error::Error for DriverError {}
+
+/// How different two images may be and still be considered matching.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tolerance {
+    /// Largest difference allowed on any channel of a pixel for it to be
+    /// considered the same.
+    pub channel: u8,
+    /// Number of pixels allowed to differ.
+    pub pixels: usize,
+}
+
+impl Default for Tolerance {
+    /// Allows small rounding differences, but no differing pixel.
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            pixels: 0,
+        }
+    }
+}
+
+/// A rendered frame, as straight RGBA pixels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Width and height in pixels.
+    pub size: [u32; 2],
+    /// RGBA pixels, row by row from the top.
+    pub pixels: Vec<u8>,
+}
+
+impl Snapshot {
+    /// Reads a snapshot from a PNG file.
+    ///
+    /// ```
+    /// use egui_speedy2d::testing::Snapshot;
+    ///
+    /// let snapshot = Snapshot { size: [1, 2], pixels: vec![1, 2, 3, 4, 5, 6, 7, 8] };
+    /// let path = std::env::temp_dir().join("egui_speedy2d_load_png.png");
+    /// snapshot.save_png(&path).unwrap();
+    /// assert_eq!(Snapshot::load_png(&path).unwrap(), snapshot);
+    /// ```
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (size, pixels) = png_io::read(path.as_ref())?;
+        Ok(Self { size, pixels })
+    }
+
+    /// Writes the snapshot to a PNG file.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        png_io::write(path.as_ref(), self.size, &self.pixels)
+    }
+
+    /// Returns the RGBA value of a pixel.
+    ///
+    /// Panics if the pixel is outside the snapshot.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.size[0] && y < self.size[1], "pixel out of bounds");
+        let i = (y as usize * self.size[0] as usize + x as usize) * 4;
+        [0, 1, 2, 3].map(|c| self.pixels[i + c])
+    }
+
+    /// Compares this snapshot to a reference.
+    ///
+    /// Returns `None` when the sizes differ.
+    ///
+    /// ```
+    /// use egui_speedy2d::testing::{Snapshot, Tolerance};
+    ///
+    /// let reference = Snapshot { size: [2, 1], pixels: vec![0, 0, 0, 255, 9, 9, 9, 255] };
+    /// let actual = Snapshot { size: [2, 1], pixels: vec![1, 0, 0, 255, 0, 0, 0, 255] };
+    /// let comparison = actual.compare(&reference, Tolerance::default()).unwrap();
+    /// assert_eq!(comparison.differing_pixels, 1);
+    /// assert!(!comparison.matches);
+    /// assert_eq!(comparison.diff.pixel(1, 0), [255, 0, 0, 255]);
+    /// ```
+    pub fn compare(&self, reference: &Snapshot, tolerance: Tolerance) -> Option<Comparison> {
+        if self.size != reference.size || self.pixels.len() != reference.pixels.len() {
+            return None;
+        }
+        let mut differing_pixels = 0;
+        let mut diff = Vec::with_capacity(self.pixels.len());
+        for (actual, expected) in self
+            .pixels
+            .chunks_exact(4)
+            .zip(reference.pixels.chunks_exact(4))
+        {
+            let differs = actual
+                .iter()
+                .zip(expected)
+                .any(|(a, e)| a.abs_diff(*e) > tolerance.channel);
+            if differs {
+                differing_pixels += 1;
+                diff.extend_from_slice(&[255, 0, 0, 255]);
+            } else {
+                // faded reference, so that the red pixels can be placed
+                let luma = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 3;
+                let faded = (luma * expected[3] as u32 / 255 / 3 + 170) as u8;
+                diff.extend_from_slice(&[faded, faded, faded, 255]);
+            }
+        }
+        Some(Comparison {
+            matches: differing_pixels <= tolerance.pixels,
+            differing_pixels,
+            diff: Snapshot {
+                size: self.size,
+                pixels: diff,
+            },
+        })
+    }
+
+    /// Compares this snapshot to the reference PNG at `path`.
+    ///
+    /// If the `UPDATE_SNAPSHOTS` environment variable is set, the snapshot is
+    /// written as the new reference instead. A missing reference is an error
+    /// otherwise, so that a test can't pass without comparing anything.
+    ///
+    /// When the comparison fails, the snapshot is written next to the
+    /// reference with a `.new.png` extension, and an image highlighting the
+    /// differing pixels in red with a `.diff.png` extension.
+    ///
+    /// ```
+    /// use egui_speedy2d::testing::{Snapshot, SnapshotError, Tolerance};
+    ///
+    /// let snapshot = Snapshot { size: [1, 1], pixels: vec![1, 2, 3, 4] };
+    /// let path = std::env::temp_dir().join("egui_speedy2d_missing_reference.png");
+    /// let _ = std::fs::remove_file(&path);
+    /// # if std::env::var_os("UPDATE_SNAPSHOTS").is_none() {
+    /// let error = snapshot.assert_matches(&path, Tolerance::default()).unwrap_err();
+    /// assert!(matches!(error, SnapshotError::MissingReference { .. }));
+    /// assert!(!path.exists());
+    /// # std::fs::remove_file(path.with_extension("new.png")).unwrap();
+    /// # }
+    /// ```
+    pub fn assert_matches(
+        &self,
+        path: impl AsRef<Path>,
+        tolerance: Tolerance,
+    ) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            self.save_png(path)?;
+            return Ok(());
+        }
+
+        let new_path = path.with_extension("new.png");
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            self.save_png(&new_path)?;
+            return Err(SnapshotError::MissingReference {
+                path: path.to_owned(),
+                new_path,
+            });
+        }
+        let reference = Snapshot::load_png(path)?;
+        match self.compare(&reference, tolerance) {
+            None => {
+                self.save_png(&new_path)?;
+                Err(SnapshotError::SizeMismatch {
+                    expected: reference.size,
+                    actual: self.size,
+                    new_path,
+                })
+            }
+            Some(comparison) if !comparison.matches => {
+                let diff_path = path.with_extension("diff.png");
+                self.save_png(&new_path)?;
+                comparison.diff.save_png(&diff_path)?;
+                Err(SnapshotError::Mismatch {
+                    differing_pixels: comparison.differing_pixels,
+                    new_path,
+                    diff_path,
+                })
+            }
+            Some(_) => {
+                // clean up the leftovers of a previous failure
+                let _ = std::fs::remove_file(&new_path);
+                let _ = std::fs::remove_file(path.with_extension("diff.png"));
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of [`Snapshot::compare`].
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    /// Whether the images match within the tolerance.
+    pub matches: bool,
+    /// Number of pixels that differ by more than the channel tolerance.
+    pub differing_pixels: usize,
+    /// The reference faded to gray, with differing pixels in red.
+    pub diff: Snapshot,
+}
+
+/// Why a [`Snapshot`] didn't match its reference.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading or writing an image failed.
+    Io(io::Error),
+    /// The reference doesn't exist, and `UPDATE_SNAPSHOTS` isn't set.
+    MissingReference { path: PathBuf, new_path: PathBuf },
+    /// The snapshot and the reference have different sizes.
+    SizeMismatch {
+        expected: [u32; 2],
+        actual: [u32; 2],
+        new_path: PathBuf,
+    },
+    /// Too many pixels differ.
+    Mismatch {
+        differing_pixels: usize,
+        new_path: PathBuf,
+        diff_path: PathBuf,
+    },
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "snapshot I/O error: {error}"),
+            Self::MissingReference { path, new_path } => write!(
+                f,
+                "reference {} doesn't exist, see {} or set UPDATE_SNAPSHOTS to write it",
+                path.display(),
+                new_path.display()
+            ),
+            Self::SizeMismatch {
+                expected,
+                actual,
+                new_path,
+            } => write!(
+                f,
+                "snapshot is {}x{} but the reference is {}x{}, see {}",
+                actual[0],
+                actual[1],
+                expected[0],
+                expected[1],
+                new_path.display()
+            ),
+            Self::Mismatch {
+                differing_pixels,
+                new_path,
+                diff_path,
+            } => write!(
+                f,
+                "{differing_pixels} pixels differ from the reference, see {} and {}",
+                new_path.display(),
+                diff_path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use speedy2d::shape::Rectangle;
+
+    /// A user event type, to run a handler that isn't a `WindowHandler<()>`.
+    struct Tick;
+
+    struct Game;
+
+    impl WindowHandler<Tick> for Game {
+        fn on_draw_before_ui(
+            &mut self,
+            _: Option<&mut WindowHelper<Tick>>,
+            canvas: &mut dyn Canvas,
+            _: &egui::Context,
+        ) {
+            canvas.clear_screen(Color::BLUE);
+        }
+
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper<Tick>>,
+            _: &mut dyn Canvas,
+            egui_ctx: &egui::Context,
+        ) {
+            let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(20., 20.));
+            egui_ctx
+                .layer_painter(egui::LayerId::background())
+                .rect_filled(rect, 0., egui::Color32::RED);
+        }
+
+        fn on_draw_after_ui(
+            &mut self,
+            _: Option<&mut WindowHelper<Tick>>,
+            canvas: &mut dyn Canvas,
+            _: &egui::Context,
+        ) {
+            canvas.fill_rectangle(Rectangle::from_tuples((10., 10.), (30., 30.)), Color::GREEN);
+        }
+    }
+
+    #[test]
+    fn driver_runs_the_drawing_callbacks_around_the_ui() {
+        let driver = Driver::new(WindowWrapper::headless(Game), (50, 50));
+        let snapshot = driver.snapshot();
+        assert_eq!(snapshot.pixel(40, 40), [0, 0, 255, 255]);
+        assert_eq!(snapshot.pixel(5, 5), [255, 0, 0, 255]);
+        assert_eq!(snapshot.pixel(15, 15), [0, 255, 0, 255]);
+        assert_eq!(snapshot.pixel(25, 25), [0, 255, 0, 255]);
+        assert!(driver.errors().is_empty());
+    }
+
+    #[cfg(feature = "accesskit")]
+    #[test]
+    fn driver_clicks_widgets_where_the_paint_transform_moved_them() {
+        use crate::{ContextTransformExt, Transform};
+
+        #[derive(Default)]
+        struct App {
+            count: usize,
+        }
+
+        impl WindowHandler for App {
+            fn on_draw(
+                &mut self,
+                _: Option<&mut WindowHelper>,
+                _: &mut dyn Canvas,
+                egui_ctx: &egui::Context,
+            ) {
+                egui_ctx.set_paint_transform(Transform::translation(50., 0.));
+                egui::CentralPanel::default().show(egui_ctx, |ui| {
+                    if ui.button("Add").clicked() {
+                        self.count += 1;
+                    }
+                    ui.label(format!("Count: {}", self.count));
+                });
+            }
+        }
+
+        let mut driver = Driver::new(WindowWrapper::headless(App::default()), (200, 200));
+        driver.click(&By::label("Add")).unwrap();
+        assert!(driver.find(&By::label("Count: 1")).is_ok());
+    }
+}
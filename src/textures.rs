@@ -0,0 +1,464 @@
+//! Textures created and freed through the egui context, so that any callback
+//! of a [`WindowHandler`](crate::WindowHandler) can show its own images in
+//! egui.
+
+use crate::{painter::check_pixels, Painter, WindowWrapperError};
+use egui::{mutex::Mutex, Context, Id};
+use speedy2d::{dimen::UVec2, image::ImageHandle};
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Creates and frees the user textures of the
+/// [`WindowWrapper`](crate::WindowWrapper) owning an egui context.
+///
+/// The textures are created when the wrapper draws the next frame of the
+/// context, so the returned ids can be painted right away:
+///
+/// ```
+/// use egui_speedy2d::ContextTexturesExt;
+/// # use egui_speedy2d::Canvas;
+/// # use speedy2d::{image::ImageHandle, window::WindowHelper};
+///
+/// struct Game {
+///     sprite: ImageHandle,
+/// }
+///
+/// impl egui_speedy2d::WindowHandler for Game {
+///     fn on_draw(&mut self, _: Option<&mut WindowHelper>, _: &mut dyn Canvas, egui_ctx: &egui::Context) {
+///         let sprite = egui_ctx.texture_id_for(&self.sprite, Default::default());
+///         egui::Window::new("Sprite").show(egui_ctx, |ui| ui.image((sprite, egui::vec2(32., 32.))));
+///     }
+/// }
+/// ```
+pub trait ContextTexturesExt {
+    /// Returns a texture id to show a speedy2d image in egui, for example
+    /// with `ui.image((texture_id, size))`.
+    ///
+    /// The same image with the same options always gets the same id, and is
+    /// drawn without being decoded or uploaded again. It is kept alive until
+    /// [`ContextTexturesExt::free_texture`] is called.
+    ///
+    /// The image keeps the smoothing mode it was created with, so only the
+    /// wrap mode of `options` is used.
+    ///
+    /// Must be called on the thread drawing the window, the only one speedy2d
+    /// images can be used on.
+    fn texture_id_for(&self, image: &ImageHandle, options: egui::TextureOptions)
+        -> egui::TextureId;
+
+    /// Creates a texture from straight (non-premultiplied) RGBA pixels, and
+    /// returns its id. The pixels are uploaded when the next frame is drawn.
+    ///
+    /// Every call creates a new texture, which lives until
+    /// [`ContextTexturesExt::free_texture`] is called: load it once and keep
+    /// the id, rather than loading it every frame. Use a
+    /// [`StreamingTexture`](crate::StreamingTexture) for pixels that change.
+    ///
+    /// Fails with [`WindowWrapperError::TexturePixels`] if `pixels` isn't
+    /// `size[0] * size[1] * 4` bytes long.
+    fn load_texture_rgba(
+        &self,
+        name: impl Into<String>,
+        size: [usize; 2],
+        pixels: impl Into<Vec<u8>>,
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError>;
+
+    /// Frees a texture created with [`ContextTexturesExt::texture_id_for`]
+    /// or [`ContextTexturesExt::load_texture_rgba`].
+    fn free_texture(&self, texture_id: egui::TextureId);
+}
+
+impl ContextTexturesExt for Context {
+    fn texture_id_for(
+        &self,
+        image: &ImageHandle,
+        options: egui::TextureOptions,
+    ) -> egui::TextureId {
+        image_texture_id(self, image, *image.size(), options)
+    }
+
+    fn load_texture_rgba(
+        &self,
+        name: impl Into<String>,
+        size: [usize; 2],
+        pixels: impl Into<Vec<u8>>,
+        options: egui::TextureOptions,
+    ) -> Result<egui::TextureId, WindowWrapperError> {
+        let pixels = pixels.into();
+        check_pixels(size, &pixels)?;
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        registry(self).lock().commands.push(Command::Pixels {
+            id,
+            name: name.into(),
+            size,
+            pixels,
+            options,
+        });
+        Ok(egui::TextureId::User(id))
+    }
+
+    fn free_texture(&self, texture_id: egui::TextureId) {
+        let egui::TextureId::User(id) = texture_id else {
+            return;
+        };
+        IMAGES.with(|images| {
+            for table in images.borrow_mut().values_mut() {
+                table.forget(id);
+            }
+        });
+        registry(self).lock().commands.push(Command::Free(id));
+    }
+}
+
+/// Textures created through contexts use user texture ids from this one on,
+/// above the [`CHOSEN_USER_TEXTURE_IDS`](crate::CHOSEN_USER_TEXTURE_IDS) and
+/// under the ids of streaming textures. They are unique across contexts.
+static NEXT_ID: AtomicU64 = AtomicU64::new(1 << 61);
+
+/// Tells the registries of the contexts apart in the image tables. A context
+/// whose data is cleared gets a new registry, with a new key.
+static NEXT_KEY: AtomicU64 = AtomicU64::new(0);
+
+/// The texture changes asked for through a context, waiting for its painter.
+struct Registry {
+    key: u64,
+    commands: Vec<Command>,
+}
+
+enum Command {
+    /// Insert the image waiting in the image table under this id.
+    Image(u64),
+    Pixels {
+        id: u64,
+        name: String,
+        size: [usize; 2],
+        pixels: Vec<u8>,
+        options: egui::TextureOptions,
+    },
+    Free(u64),
+}
+
+fn registry(ctx: &Context) -> Arc<Mutex<Registry>> {
+    ctx.data_mut(|d| {
+        d.get_temp_mut_or_insert_with(Id::new("egui_speedy2d::textures"), || {
+            Arc::new(Mutex::new(Registry {
+                key: NEXT_KEY.fetch_add(1, Ordering::Relaxed),
+                commands: vec![],
+            }))
+        })
+        .clone()
+    })
+}
+
+thread_local! {
+    /// The images registered through contexts, by image type. speedy2d images
+    /// can't leave the thread they were created on, so they are kept here
+    /// rather than in the data of the contexts.
+    static IMAGES: RefCell<HashMap<TypeId, Box<dyn ImageTable>>> = RefCell::default();
+}
+
+/// The images of a type registered through contexts.
+struct Images<I> {
+    /// Texture ids of the registered images, by context key and options.
+    ids: HashMap<(u64, I, egui::TextureOptions), u64>,
+    /// Images waiting for a painter, by texture id.
+    pending: HashMap<u64, (I, UVec2, egui::TextureOptions)>,
+}
+
+trait ImageTable {
+    /// Forgets the image registered as the user texture `id`, if any.
+    fn forget(&mut self, id: u64);
+
+    /// Forgets the images registered through the registry `key`.
+    fn forget_registry(&mut self, key: u64);
+
+    fn as_any(&mut self) -> &mut dyn Any;
+}
+
+impl<I: 'static> ImageTable for Images<I> {
+    fn forget(&mut self, id: u64) {
+        self.ids.retain(|_, texture_id| *texture_id != id);
+        self.pending.remove(&id);
+    }
+
+    fn forget_registry(&mut self, key: u64) {
+        let Self { ids, pending } = self;
+        ids.retain(|(registry, _, _), id| {
+            if *registry == key {
+                pending.remove(id);
+            }
+            *registry != key
+        });
+    }
+
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn with_images<I: 'static, T>(f: impl FnOnce(&mut Images<I>) -> T) -> T {
+    IMAGES.with(|images| {
+        let mut images = images.borrow_mut();
+        let table = images.entry(TypeId::of::<I>()).or_insert_with(|| {
+            Box::new(Images::<I> {
+                ids: HashMap::new(),
+                pending: HashMap::new(),
+            })
+        });
+        match table.as_any().downcast_mut::<Images<I>>() {
+            Some(table) => f(table),
+            None => unreachable!("image tables are stored by type"),
+        }
+    })
+}
+
+/// Returns the texture id of a renderer image registered through `ctx`,
+/// registering it the first time.
+pub(crate) fn image_texture_id<I: Clone + Hash + Eq + 'static>(
+    ctx: &Context,
+    image: &I,
+    size: UVec2,
+    options: egui::TextureOptions,
+) -> egui::TextureId {
+    let registry = registry(ctx);
+    let mut registry = registry.lock();
+    let key = (registry.key, image.clone(), options);
+    let id = with_images(|images: &mut Images<I>| {
+        if let Some(id) = images.ids.get(&key) {
+            return *id;
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        images.ids.insert(key, id);
+        images.pending.insert(id, (image.clone(), size, options));
+        registry.commands.push(Command::Image(id));
+        id
+    });
+    egui::TextureId::User(id)
+}
+
+/// Forgets the images registered through the registry `key`, once the
+/// painter applying it is gone.
+pub(crate) fn forget_registry(key: u64) {
+    // the table may already be gone when the thread ends
+    let _ = IMAGES.try_with(|images| {
+        for table in images.borrow_mut().values_mut() {
+            table.forget_registry(key);
+        }
+    });
+}
+
+/// Applies the texture changes asked for through `ctx` to its painter.
+///
+/// The images registered through `ctx` are kept until the painter is
+/// dropped, or until the registry of `ctx` is replaced.
+pub(crate) fn apply<I: Clone + 'static>(ctx: &Context, painter: &mut Painter<I>) {
+    let commands = {
+        let registry = registry(ctx);
+        let mut registry = registry.lock();
+        if let Some(old) = painter.texture_registry.replace(registry.key) {
+            if old != registry.key {
+                forget_registry(old);
+            }
+        }
+        std::mem::take(&mut registry.commands)
+    };
+    for command in commands {
+        match command {
+            Command::Image(id) => {
+                if let Some((image, size, options)) =
+                    with_images(|images: &mut Images<I>| images.pending.remove(&id))
+                {
+                    painter.insert_user_image_as(id, image, size, options);
+                }
+            }
+            Command::Pixels {
+                id,
+                name,
+                size,
+                pixels,
+                options,
+            } => {
+                painter.load_user_texture_rgba_as(id, name, size, pixels, options);
+            }
+            Command::Free(id) => painter.free_user_texture(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Renderer, SoftwareImage, SoftwareRenderer, WindowHandler, WindowWrapper};
+    use speedy2d::{image::ImageSmoothingMode, window::WindowHelper};
+
+    /// Shows an image registered from the handler's callback.
+    struct Sprite(SoftwareImage);
+
+    impl WindowHandler for Sprite {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &Context,
+        ) {
+            let id = image_texture_id(egui_ctx, &self.0, self.0.size(), Default::default());
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none())
+                .show(egui_ctx, |ui| ui.image((id, egui::vec2(10., 10.))));
+        }
+    }
+
+    #[test]
+    fn textures_registered_from_the_handler_are_painted() {
+        let mut renderer = SoftwareRenderer::new((40, 40));
+        let sprite = renderer
+            .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[255, 0, 0, 255])
+            .unwrap();
+        let mut window = WindowWrapper::headless(Sprite(sprite));
+        window.input_resize((40, 40).into());
+        let end = window.frame(&mut renderer, None);
+        assert!(end.errors.is_empty(), "{:?}", end.errors);
+        assert_eq!(renderer.pixel(5, 5), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn textures_registered_through_the_context_are_painted() {
+        let ctx = Context::default();
+        let mut renderer = SoftwareRenderer::new((40, 40));
+        let mut painter = Painter::<SoftwareImage>::default();
+        let sprite = renderer
+            .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[0, 0, 255, 255])
+            .unwrap();
+
+        let mut ids = vec![];
+        for _ in 0..2 {
+            let full_output = ctx.run(Default::default(), |ctx| {
+                let id = image_texture_id(ctx, &sprite, sprite.size(), Default::default());
+                let pixels = ctx
+                    .load_texture_rgba("green", [1, 1], [0, 255, 0, 255], Default::default())
+                    .unwrap();
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::none())
+                    .show(ctx, |ui| {
+                        ui.image((id, egui::vec2(10., 10.)));
+                        ui.image((pixels, egui::vec2(10., 10.)));
+                    });
+                ids.push((id, pixels));
+            });
+            apply(&ctx, &mut painter);
+            assert!(painter.paint(&ctx, full_output, &mut renderer).is_empty());
+            assert_eq!(renderer.pixel(5, 5), [0, 0, 255, 255]);
+            assert_eq!(renderer.pixel(5, 15), [0, 255, 0, 255]);
+        }
+        // the image keeps its id, the pixels make a new texture each time
+        assert_eq!(ids[0].0, ids[1].0);
+        assert_ne!(ids[0].1, ids[1].1);
+
+        ctx.free_texture(ids[0].0);
+        apply(&ctx, &mut painter);
+        let image = image_texture_id(&ctx, &sprite, sprite.size(), Default::default());
+        assert_ne!(image, ids[0].0);
+    }
+
+    #[test]
+    fn window_textures_are_painted_and_checked() {
+        let mut renderer = SoftwareRenderer::new((40, 40));
+        let mut window = WindowWrapper::headless(Sprite(
+            renderer
+                .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[0; 4])
+                .unwrap(),
+        ));
+        window.input_resize((40, 40).into());
+        let error = window.load_texture_rgba("short", [2, 2], [0; 4], Default::default());
+        assert!(matches!(
+            error,
+            Err(WindowWrapperError::TexturePixels {
+                size: [2, 2],
+                len: 4
+            })
+        ));
+
+        let green = window
+            .load_texture_rgba("green", [1, 1], [0, 255, 0, 255], Default::default())
+            .unwrap();
+        window.frame(&mut renderer, None);
+        assert!(window.painter.textures().iter().any(|t| t.id == green));
+        window.free_texture(green);
+        window.frame(&mut renderer, None);
+        assert!(window.painter.textures().iter().all(|t| t.id != green));
+    }
+
+    #[test]
+    fn images_get_a_texture_per_options() {
+        let ctx = Context::default();
+        let mut renderer = SoftwareRenderer::new((1, 1));
+        let sprite = renderer
+            .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[0; 4])
+            .unwrap();
+        let repeat = egui::TextureOptions {
+            wrap_mode: egui::TextureWrapMode::Repeat,
+            ..Default::default()
+        };
+        let clamped = image_texture_id(&ctx, &sprite, sprite.size(), Default::default());
+        let repeated = image_texture_id(&ctx, &sprite, sprite.size(), repeat);
+        assert_ne!(clamped, repeated);
+        assert_eq!(
+            image_texture_id(&ctx, &sprite, sprite.size(), repeat),
+            repeated
+        );
+
+        let mut painter = Painter::<SoftwareImage>::default();
+        apply(&ctx, &mut painter);
+        let options = |id| {
+            painter
+                .textures()
+                .into_iter()
+                .find(|t| t.id == id)
+                .map(|t| t.options)
+        };
+        assert_eq!(options(repeated), Some(repeat));
+    }
+
+    #[test]
+    fn images_are_forgotten_with_their_painter() {
+        let ctx = Context::default();
+        let mut renderer = SoftwareRenderer::new((1, 1));
+        let sprite = renderer
+            .create_image((1, 1).into(), ImageSmoothingMode::Linear, &[0; 4])
+            .unwrap();
+        let registered = || {
+            with_images(|images: &mut Images<SoftwareImage>| {
+                images
+                    .ids
+                    .keys()
+                    .filter(|(_, image, _)| *image == sprite)
+                    .count()
+            })
+        };
+
+        image_texture_id(&ctx, &sprite, sprite.size(), Default::default());
+        let mut painter = Painter::<SoftwareImage>::default();
+        apply(&ctx, &mut painter);
+        assert_eq!(registered(), 1);
+        drop(painter);
+        assert_eq!(registered(), 0);
+
+        // clearing the data of the context replaces its registry
+        let mut painter = Painter::<SoftwareImage>::default();
+        image_texture_id(&ctx, &sprite, sprite.size(), Default::default());
+        apply(&ctx, &mut painter);
+        ctx.data_mut(|d| d.clear());
+        image_texture_id(&ctx, &sprite, sprite.size(), Default::default());
+        apply(&ctx, &mut painter);
+        assert_eq!(registered(), 1);
+    }
+}
@@ -0,0 +1,214 @@
+//! 2D affine transforms applied to everything an egui context paints.
+//!
+//! This is what allows egui widgets to live in "world space", for example
+//! attached to game entities and following the camera's pan, zoom and rotation.
+
+use egui::{Context, Id, Pos2, Rect};
+
+/// A 2D affine transform.
+///
+/// A point `(x, y)` is mapped to
+/// `(m11 * x + m12 * y + tx, m21 * x + m22 * y + ty)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub m11: f32,
+    pub m12: f32,
+    pub m21: f32,
+    pub m22: f32,
+    pub tx: f32,
+    pub ty: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    /// The transform that leaves every point untouched.
+    pub const IDENTITY: Self = Self {
+        m11: 1.,
+        m12: 0.,
+        m21: 0.,
+        m22: 1.,
+        tx: 0.,
+        ty: 0.,
+    };
+
+    /// A translation by `(x, y)`.
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self {
+            tx: x,
+            ty: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// A uniform scale around the origin.
+    pub fn scale(factor: f32) -> Self {
+        Self {
+            m11: factor,
+            m22: factor,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// A clockwise rotation (in screen coordinates, where y points down) of
+    /// `angle` radians around the origin.
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            m11: cos,
+            m12: -sin,
+            m21: sin,
+            m22: cos,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Returns the transform that applies `self` first, then `other`.
+    ///
+    /// ```
+    /// use egui_speedy2d::Transform;
+    ///
+    /// let t = Transform::scale(2.).then(Transform::translation(10., 0.));
+    /// assert_eq!(t.apply(egui::pos2(1., 1.)), egui::pos2(12., 2.));
+    /// ```
+    pub fn then(self, other: Self) -> Self {
+        Self {
+            m11: other.m11 * self.m11 + other.m12 * self.m21,
+            m12: other.m11 * self.m12 + other.m12 * self.m22,
+            m21: other.m21 * self.m11 + other.m22 * self.m21,
+            m22: other.m21 * self.m12 + other.m22 * self.m22,
+            tx: other.m11 * self.tx + other.m12 * self.ty + other.tx,
+            ty: other.m21 * self.tx + other.m22 * self.ty + other.ty,
+        }
+    }
+
+    /// Maps a point through this transform.
+    pub fn apply(&self, pos: Pos2) -> Pos2 {
+        Pos2::new(
+            self.m11 * pos.x + self.m12 * pos.y + self.tx,
+            self.m21 * pos.x + self.m22 * pos.y + self.ty,
+        )
+    }
+
+    /// Returns the inverse transform, or `None` if this transform collapses
+    /// the plane (for example a zero scale).
+    ///
+    /// ```
+    /// use egui_speedy2d::Transform;
+    ///
+    /// let t = Transform::rotation(0.5).then(Transform::translation(3., 4.));
+    /// let p = t.inverse().unwrap().apply(t.apply(egui::pos2(7., -2.)));
+    /// assert!((p - egui::pos2(7., -2.)).length() < 1e-4);
+    /// ```
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.m11 * self.m22 - self.m12 * self.m21;
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+        let m11 = self.m22 / det;
+        let m12 = -self.m12 / det;
+        let m21 = -self.m21 / det;
+        let m22 = self.m11 / det;
+        Some(Self {
+            m11,
+            m12,
+            m21,
+            m22,
+            tx: -(m11 * self.tx + m12 * self.ty),
+            ty: -(m21 * self.tx + m22 * self.ty),
+        })
+    }
+
+    /// Maps a rectangle through this transform.
+    ///
+    /// If the transformed rectangle is no longer axis-aligned, its axis-aligned
+    /// bounding box is returned instead. This is the fallback used for clip
+    /// rects: rotated content is clipped to the bounding box of its rotated
+    /// clip rect, so it is never cut off but may overflow slightly.
+    pub fn apply_rect(&self, rect: Rect) -> Rect {
+        Rect::from_points(&[
+            self.apply(rect.left_top()),
+            self.apply(rect.right_top()),
+            self.apply(rect.left_bottom()),
+            self.apply(rect.right_bottom()),
+        ])
+    }
+}
+
+/// Extension methods to set the [`Transform`] applied to an egui context.
+///
+/// The transform is stored in the context itself so it can be changed from any
+/// [`WindowHandler`](crate::WindowHandler) callback, for example once per frame
+/// in `on_draw` to follow a camera. It is applied by
+/// [`WindowWrapper::draw`](crate::WindowWrapper::draw) to vertex positions and
+/// clip rects, and its inverse is applied to pointer events so widgets stay
+/// interactive.
+pub trait ContextTransformExt {
+    /// Sets the transform applied to everything painted by this context.
+    fn set_paint_transform(&self, transform: Transform);
+
+    /// Returns the transform applied to everything painted by this context.
+    fn paint_transform(&self) -> Transform;
+}
+
+impl ContextTransformExt for Context {
+    fn set_paint_transform(&self, transform: Transform) {
+        self.data_mut(|d| d.insert_temp(transform_id(), transform));
+    }
+
+    fn paint_transform(&self) -> Transform {
+        self.data(|d| d.get_temp(transform_id()))
+            .unwrap_or_default()
+    }
+}
+
+fn transform_id() -> Id {
+    Id::new("egui_speedy2d::paint_transform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::Driver, Canvas, WindowHandler, WindowWrapper};
+    use speedy2d::window::WindowHelper;
+    use std::{cell::Cell, rc::Rc};
+
+    /// A button at the origin of a context moved 50 pixels to the right.
+    struct Moved(Rc<Cell<usize>>);
+
+    impl WindowHandler for Moved {
+        fn on_draw(
+            &mut self,
+            _: Option<&mut WindowHelper>,
+            _: &mut dyn Canvas,
+            egui_ctx: &Context,
+        ) {
+            egui_ctx.set_paint_transform(Transform::translation(50., 0.));
+            egui::Area::new("button".into())
+                .fixed_pos(Pos2::ZERO)
+                .show(egui_ctx, |ui| {
+                    let button = egui::Button::new("").min_size(egui::vec2(20., 20.));
+                    if ui.add(button).clicked() {
+                        self.0.set(self.0.get() + 1);
+                    }
+                });
+        }
+    }
+
+    #[test]
+    fn clicks_go_back_through_the_inverse_transform() {
+        let clicks = Rc::new(Cell::new(0));
+        let window = WindowWrapper::headless(Moved(clicks.clone()));
+        let mut driver = Driver::new(window, (100, 100));
+
+        // where the button would be without the transform
+        driver.click_at(egui::pos2(10., 10.));
+        assert_eq!(clicks.get(), 0);
+        driver.click_at(egui::pos2(60., 10.));
+        assert_eq!(clicks.get(), 1);
+    }
+}
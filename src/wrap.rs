@@ -0,0 +1,216 @@
+//! Emulation of egui texture wrap modes.
+//!
+//! speedy2d always samples textures with clamp-to-edge, so
+//! [`Repeat`](egui::TextureWrapMode::Repeat) and
+//! [`MirroredRepeat`](egui::TextureWrapMode::MirroredRepeat) are emulated by
+//! splitting triangles along the integer lines of their UV coordinates, then
+//! folding the UVs of every piece back into the `[0, 1]` range.
+
+use egui::{epaint::Vertex, Color32, TextureWrapMode};
+
+/// Beyond this many tiles in a single triangle, the triangle is drawn
+/// clamped instead of being split.
+const MAX_TILES: f32 = 4096.;
+
+/// Splits a triangle so that it can be drawn with clamp-to-edge sampling while
+/// looking like it was drawn with `wrap_mode`, and pushes the pieces to
+/// `triangles`.
+///
+/// The pieces have their UV coordinates inside `[0, 1]`, but their winding
+/// order is unspecified.
+///
+/// Returns `false` when the triangle spans more than [`MAX_TILES`] tiles, and
+/// was pushed as it is, to be drawn clamped.
+pub(crate) fn split_triangle(
+    triangle: [Vertex; 3],
+    wrap_mode: TextureWrapMode,
+    triangles: &mut Vec<[Vertex; 3]>,
+) -> bool {
+    if wrap_mode == TextureWrapMode::ClampToEdge
+        || triangle
+            .iter()
+            .all(|v| (0. ..=1.).contains(&v.uv.x) && (0. ..=1.).contains(&v.uv.y))
+    {
+        triangles.push(triangle);
+        return true;
+    }
+
+    let (min_u, max_u) = range(triangle.iter().map(|v| v.uv.x));
+    let (min_v, max_v) = range(triangle.iter().map(|v| v.uv.y));
+    let (first_u, last_u) = (min_u.floor(), max_u.ceil().max(min_u.floor() + 1.));
+    let (first_v, last_v) = (min_v.floor(), max_v.ceil().max(min_v.floor() + 1.));
+    let tiles = (last_u - first_u) * (last_v - first_v);
+    if !tiles.is_finite() || tiles > MAX_TILES {
+        triangles.push(triangle);
+        return false;
+    }
+
+    let mut tile_u = first_u;
+    while tile_u < last_u {
+        let mut tile_v = first_v;
+        while tile_v < last_v {
+            let mut polygon = triangle.to_vec();
+            polygon = clip(&polygon, |v| v.uv.x - tile_u);
+            polygon = clip(&polygon, |v| tile_u + 1. - v.uv.x);
+            polygon = clip(&polygon, |v| v.uv.y - tile_v);
+            polygon = clip(&polygon, |v| tile_v + 1. - v.uv.y);
+            for v in &mut polygon {
+                v.uv.x = fold(v.uv.x - tile_u, tile_u, wrap_mode);
+                v.uv.y = fold(v.uv.y - tile_v, tile_v, wrap_mode);
+            }
+            for i in 1..polygon.len().saturating_sub(1) {
+                triangles.push([polygon[0], polygon[i], polygon[i + 1]]);
+            }
+            tile_v += 1.;
+        }
+        tile_u += 1.;
+    }
+    true
+}
+
+fn range(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    values.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| {
+        (min.min(x), max.max(x))
+    })
+}
+
+/// Maps a coordinate local to a tile into the texture, mirroring every other
+/// tile for [`TextureWrapMode::MirroredRepeat`].
+fn fold(local: f32, tile: f32, wrap_mode: TextureWrapMode) -> f32 {
+    let local = local.clamp(0., 1.);
+    if wrap_mode == TextureWrapMode::MirroredRepeat && tile.rem_euclid(2.) == 1. {
+        1. - local
+    } else {
+        local
+    }
+}
+
+/// Sutherland–Hodgman clipping of a convex polygon against the half-plane
+/// where `distance` is positive.
+fn clip(polygon: &[Vertex], distance: impl Fn(&Vertex) -> f32) -> Vec<Vertex> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        let (d_current, d_next) = (distance(current), distance(next));
+        if d_current >= 0. {
+            clipped.push(*current);
+        }
+        if (d_current >= 0.) != (d_next >= 0.) {
+            clipped.push(lerp(current, next, d_current / (d_current - d_next)));
+        }
+    }
+    clipped
+}
+
+fn lerp(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Vertex {
+        pos: a.pos + (b.pos - a.pos) * t,
+        uv: a.uv + (b.uv - a.uv) * t,
+        color: Color32::from_rgba_premultiplied(
+            channel(a.color.r(), b.color.r()),
+            channel(a.color.g(), b.color.g()),
+            channel(a.color.b(), b.color.b()),
+            channel(a.color.a(), b.color.a()),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, Pos2};
+
+    /// A triangle whose positions are its UV coordinates scaled by 10.
+    fn triangle(uvs: [[f32; 2]; 3]) -> [Vertex; 3] {
+        uvs.map(|[u, v]| Vertex {
+            pos: pos2(u * 10., v * 10.),
+            uv: pos2(u, v),
+            color: Color32::WHITE,
+        })
+    }
+
+    fn area(p: [Pos2; 3]) -> f32 {
+        ((p[1] - p[0]).x * (p[2] - p[0]).y - (p[1] - p[0]).y * (p[2] - p[0]).x).abs() / 2.
+    }
+
+    fn center(p: [Pos2; 3]) -> Pos2 {
+        ((p[0].to_vec2() + p[1].to_vec2() + p[2].to_vec2()) / 3.).to_pos2()
+    }
+
+    fn wrap(x: f32, wrap_mode: TextureWrapMode) -> f32 {
+        match wrap_mode {
+            TextureWrapMode::MirroredRepeat if x.rem_euclid(2.) > 1. => 2. - x.rem_euclid(2.),
+            _ => x.rem_euclid(1.),
+        }
+    }
+
+    /// Checks that the pieces cover the triangle, and sample the texture
+    /// where the triangle would with `wrap_mode`.
+    fn assert_split(uvs: [[f32; 2]; 3], wrap_mode: TextureWrapMode, min_pieces: usize) {
+        let original = triangle(uvs);
+        let mut pieces = vec![];
+        assert!(split_triangle(original, wrap_mode, &mut pieces));
+        assert!(pieces.len() >= min_pieces, "{} pieces", pieces.len());
+
+        let covered = pieces.iter().map(|t| area(t.map(|v| v.pos))).sum::<f32>();
+        let expected = area(original.map(|v| v.pos));
+        assert!((covered - expected).abs() < 1e-3, "{covered} != {expected}");
+
+        for piece in pieces {
+            for v in piece {
+                assert!((0. ..=1.).contains(&v.uv.x) && (0. ..=1.).contains(&v.uv.y));
+            }
+            // UVs are affine inside a piece, and its center is inside a tile
+            let pos = center(piece.map(|v| v.pos));
+            let uv = center(piece.map(|v| v.uv));
+            let expected = pos / 10.;
+            assert!(
+                (uv.x - wrap(expected.x, wrap_mode)).abs() < 1e-3,
+                "{uv:?} at {pos:?}"
+            );
+            assert!(
+                (uv.y - wrap(expected.y, wrap_mode)).abs() < 1e-3,
+                "{uv:?} at {pos:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn clamped_triangles_are_kept() {
+        let original = triangle([[-0.5, 0.], [1.5, 0.], [0., 2.]]);
+        let mut pieces = vec![];
+        assert!(split_triangle(
+            original,
+            TextureWrapMode::ClampToEdge,
+            &mut pieces
+        ));
+        assert_eq!(pieces, [original]);
+    }
+
+    #[test]
+    fn triangles_spanning_too_many_tiles_are_kept() {
+        let original = triangle([[0., 0.], [1000., 0.], [0., 1000.]]);
+        let mut pieces = vec![];
+        assert!(!split_triangle(
+            original,
+            TextureWrapMode::Repeat,
+            &mut pieces
+        ));
+        assert_eq!(pieces, [original]);
+    }
+
+    #[test]
+    fn splits_triangles_crossing_a_tile_edge() {
+        let uvs = [[0.5, 0.2], [1.5, 0.2], [0.5, 0.8]];
+        assert_split(uvs, TextureWrapMode::Repeat, 2);
+        assert_split(uvs, TextureWrapMode::MirroredRepeat, 2);
+    }
+
+    #[test]
+    fn splits_triangles_spanning_several_tiles() {
+        let uvs = [[-1.5, -0.5], [2.5, -0.5], [-1.5, 2.5]];
+        assert_split(uvs, TextureWrapMode::Repeat, 6);
+        assert_split(uvs, TextureWrapMode::MirroredRepeat, 6);
+    }
+}